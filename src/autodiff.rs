@@ -1,4 +1,6 @@
 pub mod grad;
+pub mod gradcheck;
+pub mod interval;
 pub mod real;
 pub mod node;
 pub mod tape;
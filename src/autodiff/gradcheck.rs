@@ -0,0 +1,104 @@
+use super::real::Real;
+use super::tape::Tape;
+use super::var::Var;
+
+/// The gradient computed by `backprop` next to one computed with central finite differences.
+#[derive(Clone, Debug, Default, PartialEq, PartialOrd)]
+pub struct GradCheck<T: Real> {
+    pub(crate) analytic: Vec<T>,
+    pub(crate) numeric: Vec<T>,
+}
+
+impl<T: Real> GradCheck<T> {
+    /// Returns the gradient computed by `backprop`.
+    #[inline]
+    pub fn analytic(&self) -> &Vec<T> {
+        &self.analytic
+    }
+
+    /// Returns the gradient computed with finite differences.
+    #[inline]
+    pub fn numeric(&self) -> &Vec<T> {
+        &self.numeric
+    }
+
+    /// Returns `|analytic - numeric| / max(|analytic|, |numeric|)` for each input, or zero where both are zero.
+    #[inline]
+    pub fn rel_errors(&self) -> Vec<T> {
+        debug_assert_eq!(self.analytic.len(), self.numeric.len());
+        self.analytic
+            .iter()
+            .zip(self.numeric.iter())
+            .map(|(&a, &n)| {
+                let scale = a.abs().max(n.abs());
+                if scale == T::zero() { T::zero() } else { (a - n).abs() / scale }
+            })
+            .collect()
+    }
+
+    /// Returns the largest relative error over all inputs.
+    #[inline]
+    pub fn max_rel_error(&self) -> T {
+        self.rel_errors().into_iter().fold(T::zero(), |acc, x| acc.max(x))
+    }
+}
+
+/// Returns the step size used to differentiate at `x`, scaled to its magnitude.
+#[inline]
+pub(crate) fn step_size<T: Real>(x: T) -> T {
+    T::EPSILON.cbrt() * x.abs().max(T::one())
+}
+
+/// Compares the gradient of `f` at `inputs` found by `backprop` against central finite differences.
+#[inline]
+pub fn gradcheck<T: Real>(f: impl for<'t> Fn(&[Var<'t, T>]) -> Var<'t, T>, inputs: &[T]) -> GradCheck<T> {
+    let eval = |inputs: &[T]| {
+        let tape = Tape::new();
+        f(&tape.new_vars(&inputs.to_vec())).val()
+    };
+
+    let tape = Tape::new();
+    let analytic = f(&tape.new_vars(&inputs.to_vec())).backprop().wrt_inputs().to_vec();
+
+    let mut numeric = Vec::with_capacity(inputs.len());
+    for i in 0..inputs.len() {
+        let h = step_size(inputs[i]);
+        let mut plus = inputs.to_vec();
+        let mut minus = inputs.to_vec();
+        plus[i] = plus[i] + h;
+        minus[i] = minus[i] - h;
+
+        numeric.push((eval(&plus) - eval(&minus)) / (h + h));
+    }
+
+    GradCheck {
+        analytic,
+        numeric,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::autodiff::real::operations::UnaryOperations;
+
+    use super::*;
+
+    #[test]
+    fn gradcheck_test() {
+        // z = sin(xy) + x^3 / (1 + y^2)
+        let check = gradcheck(|v| (v[0] * v[1]).sin() + v[0].powi(3) / (v[1].square() + 1.0), &[0.7, -1.3]);
+
+        assert_eq!(check.analytic().len(), 2);
+        assert!(check.max_rel_error() < 1E-8);
+    }
+
+    #[test]
+    fn gradcheck_catches_bad_partial() {
+        // a custom square with a wrong derivative
+        let check = gradcheck(|v| v[0].custom_unary(v[0].val() * v[0].val(), v[0].val()), &[2.0f64]);
+
+        assert_eq!(check.analytic(), &[2.0]);
+        assert!((check.numeric()[0] - 4.0).abs() < 1E-6);
+        assert!((check.max_rel_error() - 0.5).abs() < 1E-6);
+    }
+}
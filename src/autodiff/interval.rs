@@ -0,0 +1,625 @@
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
+
+use crate::network::activation_fn::{Activate, ActivationFn};
+
+use super::real::operations::{BinaryOperations, OperateWithReal, TensorOperations, UnaryOperations};
+use super::real::Real;
+
+/// A closed interval of real numbers. Operations on intervals return an interval containing every result of the operation
+/// on values inside the operands, so running a `Network` on intervals bounds its output over a whole box of inputs.
+/// Results are widened outward by a relative `EPSILON` to account for rounding.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Interval<T: Real> {
+    pub(crate) lo: T,
+    pub(crate) hi: T,
+}
+
+impl<T: Real> Interval<T> {
+    #[inline]
+    pub fn new(lo: T, hi: T) -> Self {
+        assert!(lo <= hi, "Lower bound must not be greater than upper bound");
+        Interval { lo, hi }
+    }
+
+    /// Creates an interval containing only `x`.
+    #[inline]
+    pub fn point(x: T) -> Self {
+        Interval { lo: x, hi: x }
+    }
+
+    #[inline]
+    pub fn lo(&self) -> T {
+        self.lo
+    }
+
+    #[inline]
+    pub fn hi(&self) -> T {
+        self.hi
+    }
+
+    #[inline]
+    pub fn width(&self) -> T {
+        self.hi - self.lo
+    }
+
+    #[inline]
+    pub fn mid(&self) -> T {
+        (self.lo + self.hi) / (T::one() + T::one())
+    }
+
+    #[inline]
+    pub fn contains(&self, x: T) -> bool {
+        self.lo <= x && x <= self.hi
+    }
+
+    #[inline]
+    pub fn contains_zero(&self) -> bool {
+        self.contains(T::zero())
+    }
+
+    /// Creates the interval from `lo` to `hi`, pushed outward to cover rounding error in computing them.
+    #[inline]
+    fn rounded(lo: T, hi: T) -> Self {
+        let down = if lo.is_finite() { lo - lo.abs() * T::EPSILON } else { lo };
+        let up = if hi.is_finite() { hi + hi.abs() * T::EPSILON } else { hi };
+        Interval { lo: down, hi: up }
+    }
+
+    /// Applies a nondecreasing function to both bounds.
+    #[inline]
+    fn increasing(self, f: impl Fn(T) -> T) -> Self {
+        Self::rounded(f(self.lo), f(self.hi))
+    }
+
+    /// Applies a nonincreasing function to both bounds.
+    #[inline]
+    fn decreasing(self, f: impl Fn(T) -> T) -> Self {
+        Self::rounded(f(self.hi), f(self.lo))
+    }
+
+    /// Returns the smallest interval containing `f` applied to every corner of `self` × `other`.
+    #[inline]
+    fn corners(self, other: Self, f: impl Fn(T, T) -> T) -> Self {
+        let values = [f(self.lo, other.lo), f(self.lo, other.hi), f(self.hi, other.lo), f(self.hi, other.hi)];
+        let lo = values.iter().fold(values[0], |acc, &x| acc.min(x));
+        let hi = values.iter().fold(values[0], |acc, &x| acc.max(x));
+        Self::rounded(lo, hi)
+    }
+
+    /// Returns the interval of `|x|` for all `x` in `self`, without rounding.
+    #[inline]
+    fn abs_bounds(self) -> (T, T) {
+        if self.lo >= T::zero() {
+            (self.lo, self.hi)
+        }
+        else if self.hi <= T::zero() {
+            (-self.hi, -self.lo)
+        }
+        else {
+            (T::zero(), (-self.lo).max(self.hi))
+        }
+    }
+
+    /// Bounds a function with period `2π` whose maximum is at `peak` and minimum at `peak + π`, such as sine.
+    #[inline]
+    fn periodic(self, f: impl Fn(T) -> T, peak: T) -> Self {
+        let pi = T::from_f64(std::f64::consts::PI);
+        let two_pi = pi + pi;
+        if !self.width().is_finite() || self.width() >= two_pi {
+            return Interval { lo: -T::one(), hi: T::one() };
+        }
+
+        // whether some `x` in `self` is `at` plus a whole number of periods
+        let hits = |at: T| (self.hi - at) / two_pi >= ((self.lo - at) / two_pi).ceil();
+        let (a, b) = (f(self.lo), f(self.hi));
+        let lo = if hits(peak + pi) { -T::one() } else { a.min(b) };
+        let hi = if hits(peak) { T::one() } else { a.max(b) };
+        Self::rounded(lo, hi).intersect(-T::one(), T::one())
+    }
+
+    #[inline]
+    fn intersect(self, lo: T, hi: T) -> Self {
+        Interval { lo: self.lo.max(lo), hi: self.hi.min(hi) }
+    }
+
+    /// The interval containing every representable value.
+    #[inline]
+    fn everything() -> Self {
+        Interval { lo: T::MIN + T::MIN, hi: T::MAX + T::MAX }
+    }
+}
+
+impl<T: Real> UnaryOperations for Interval<T> {
+    #[inline]
+    fn recip(self) -> Self {
+        if self.contains_zero() {
+            return Self::everything();
+        }
+        self.decreasing(T::recip)
+    }
+
+    #[inline]
+    fn abs(self) -> Self {
+        let (lo, hi) = self.abs_bounds();
+        Interval { lo, hi }
+    }
+
+    #[inline]
+    fn signum(self) -> Self {
+        Interval { lo: self.lo.signum(), hi: self.hi.signum() }
+    }
+
+    #[inline]
+    fn sqrt(self) -> Self {
+        self.increasing(T::sqrt)
+    }
+
+    #[inline]
+    fn exp(self) -> Self {
+        self.increasing(T::exp)
+    }
+
+    #[inline]
+    fn exp2(self) -> Self {
+        self.increasing(T::exp2)
+    }
+
+    #[inline]
+    fn ln(self) -> Self {
+        self.increasing(T::ln)
+    }
+
+    #[inline]
+    fn log2(self) -> Self {
+        self.increasing(T::log2)
+    }
+
+    #[inline]
+    fn log10(self) -> Self {
+        self.increasing(T::log10)
+    }
+
+    #[inline]
+    fn cbrt(self) -> Self {
+        self.increasing(T::cbrt)
+    }
+
+    #[inline]
+    fn sin(self) -> Self {
+        let half_pi = T::from_f64(std::f64::consts::FRAC_PI_2);
+        self.periodic(T::sin, half_pi)
+    }
+
+    #[inline]
+    fn cos(self) -> Self {
+        self.periodic(T::cos, T::zero())
+    }
+
+    #[inline]
+    fn tan(self) -> Self {
+        let pi = T::from_f64(std::f64::consts::PI);
+        let half_pi = pi / (T::one() + T::one());
+
+        // tan is increasing between its asymptotes
+        if self.width() >= pi || (self.hi - half_pi) / pi >= ((self.lo - half_pi) / pi).ceil() {
+            return Self::everything();
+        }
+        self.increasing(T::tan)
+    }
+
+    #[inline]
+    fn asin(self) -> Self {
+        self.increasing(T::asin)
+    }
+
+    #[inline]
+    fn acos(self) -> Self {
+        self.decreasing(T::acos)
+    }
+
+    #[inline]
+    fn atan(self) -> Self {
+        self.increasing(T::atan)
+    }
+
+    #[inline]
+    fn exp_m1(self) -> Self {
+        self.increasing(T::exp_m1)
+    }
+
+    #[inline]
+    fn ln_1p(self) -> Self {
+        self.increasing(T::ln_1p)
+    }
+
+    #[inline]
+    fn sinh(self) -> Self {
+        self.increasing(T::sinh)
+    }
+
+    #[inline]
+    fn cosh(self) -> Self {
+        let (lo, hi) = self.abs_bounds();
+        Self::rounded(lo.cosh(), hi.cosh())
+    }
+
+    #[inline]
+    fn tanh(self) -> Self {
+        self.increasing(T::tanh)
+    }
+
+    #[inline]
+    fn asinh(self) -> Self {
+        self.increasing(T::asinh)
+    }
+
+    #[inline]
+    fn acosh(self) -> Self {
+        self.increasing(T::acosh)
+    }
+
+    #[inline]
+    fn atanh(self) -> Self {
+        self.increasing(T::atanh)
+    }
+
+    #[inline]
+    fn trunc(self) -> Self {
+        Interval { lo: self.lo.trunc(), hi: self.hi.trunc() }
+    }
+
+    #[inline]
+    fn floor(self) -> Self {
+        Interval { lo: self.lo.floor(), hi: self.hi.floor() }
+    }
+
+    #[inline]
+    fn ceil(self) -> Self {
+        Interval { lo: self.lo.ceil(), hi: self.hi.ceil() }
+    }
+
+    #[inline]
+    fn round(self) -> Self {
+        Interval { lo: self.lo.round(), hi: self.hi.round() }
+    }
+
+    #[inline]
+    fn square(self) -> Self {
+        let (lo, hi) = self.abs_bounds();
+        Self::rounded(lo * lo, hi * hi)
+    }
+
+    #[inline]
+    fn powi(self, n: i32) -> Self {
+        if n == i32::MIN {
+            // -n would overflow, so this is the square of the half power
+            return self.powi(n / 2).square();
+        }
+        if n < 0 {
+            return self.powi(-n).recip();
+        }
+        if n % 2 == 0 {
+            let (lo, hi) = self.abs_bounds();
+            return Self::rounded(lo.powi(n), hi.powi(n));
+        }
+        self.increasing(|x| x.powi(n))
+    }
+}
+
+impl<T: Real> BinaryOperations for Interval<T> {
+    #[inline]
+    fn log(self, base: Self) -> Self {
+        self.ln() / base.ln()
+    }
+
+    /// Only defined for positive `self` unless `n` is a single integer, as with `f64::powf`.
+    #[inline]
+    fn powf(self, n: Self) -> Self {
+        if n.lo == n.hi && n.lo == n.lo.round() && n.lo.abs().to_f64() <= i32::MAX as f64 {
+            return self.powi(n.lo.to_f64() as i32);
+        }
+        (self.ln() * n).exp()
+    }
+
+    #[inline]
+    fn min(self, other: Self) -> Self {
+        Interval { lo: self.lo.min(other.lo), hi: self.hi.min(other.hi) }
+    }
+
+    #[inline]
+    fn max(self, other: Self) -> Self {
+        Interval { lo: self.lo.max(other.lo), hi: self.hi.max(other.hi) }
+    }
+
+    #[inline]
+    fn hypot(self, other: Self) -> Self {
+        (self.square() + other.square()).sqrt()
+    }
+
+    #[inline]
+    fn atan2(self, other: Self) -> Self {
+        // away from the negative x axis, the extremes are at the corners
+        if other.lo > T::zero() {
+            return self.corners(other, T::atan2);
+        }
+
+        let pi = T::from_f64(std::f64::consts::PI);
+        Self::rounded(-pi, pi)
+    }
+
+    #[inline]
+    fn mul_add(self, a: Self, b: Self) -> Self {
+        self * a + b
+    }
+}
+
+impl<T: Real> Neg for Interval<T> {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self::Output {
+        Interval { lo: -self.hi, hi: -self.lo }
+    }
+}
+
+impl<T: Real> Add for Interval<T> {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::rounded(self.lo + rhs.lo, self.hi + rhs.hi)
+    }
+}
+
+impl<T: Real> Sub for Interval<T> {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self::rounded(self.lo - rhs.hi, self.hi - rhs.lo)
+    }
+}
+
+impl<T: Real> Mul for Interval<T> {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: Self) -> Self::Output {
+        self.corners(rhs, |x, y| x * y)
+    }
+}
+
+impl<T: Real> Div for Interval<T> {
+    type Output = Self;
+
+    #[inline]
+    fn div(self, rhs: Self) -> Self::Output {
+        if rhs.contains_zero() {
+            return Self::everything();
+        }
+        self.corners(rhs, |x, y| x / y)
+    }
+}
+
+impl<T: Real> Rem for Interval<T> {
+    type Output = Self;
+
+    /// The remainder has the sign of `self` and is smaller in magnitude than `rhs`.
+    #[inline]
+    fn rem(self, rhs: Self) -> Self::Output {
+        let (_, max_divisor) = rhs.abs_bounds();
+        let lo = if self.lo >= T::zero() { T::zero() } else { self.lo.max(-max_divisor) };
+        let hi = if self.hi <= T::zero() { T::zero() } else { self.hi.min(max_divisor) };
+        Interval { lo, hi }
+    }
+}
+
+impl<T: Real> BinaryOperations<T> for Interval<T> {
+    #[inline]
+    fn log(self, base: T) -> Self {
+        self.log(Self::point(base))
+    }
+
+    #[inline]
+    fn powf(self, n: T) -> Self {
+        self.powf(Self::point(n))
+    }
+
+    #[inline]
+    fn min(self, other: T) -> Self {
+        BinaryOperations::min(self, Self::point(other))
+    }
+
+    #[inline]
+    fn max(self, other: T) -> Self {
+        BinaryOperations::max(self, Self::point(other))
+    }
+
+    #[inline]
+    fn hypot(self, other: T) -> Self {
+        self.hypot(Self::point(other))
+    }
+
+    #[inline]
+    fn atan2(self, other: T) -> Self {
+        self.atan2(Self::point(other))
+    }
+
+    #[inline]
+    fn mul_add(self, a: T, b: T) -> Self {
+        self.mul_add(Self::point(a), Self::point(b))
+    }
+}
+
+impl<T: Real> Add<T> for Interval<T> {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: T) -> Self::Output {
+        self + Self::point(rhs)
+    }
+}
+
+impl<T: Real> Sub<T> for Interval<T> {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: T) -> Self::Output {
+        self - Self::point(rhs)
+    }
+}
+
+impl<T: Real> Mul<T> for Interval<T> {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: T) -> Self::Output {
+        self * Self::point(rhs)
+    }
+}
+
+impl<T: Real> Div<T> for Interval<T> {
+    type Output = Self;
+
+    #[inline]
+    fn div(self, rhs: T) -> Self::Output {
+        self / Self::point(rhs)
+    }
+}
+
+impl<T: Real> Rem<T> for Interval<T> {
+    type Output = Self;
+
+    #[inline]
+    fn rem(self, rhs: T) -> Self::Output {
+        self % Self::point(rhs)
+    }
+}
+
+impl<T: Real> OperateWithReal<T> for Interval<T> {
+}
+
+impl<T: Real> TensorOperations<T> for Interval<T> {
+    #[inline]
+    fn matmul(lhs: &[Self], rhs: &[Self], rows: usize, cols: usize) -> Vec<Self> {
+        assert!(rows > 0 && cols > 0 && lhs.len().is_multiple_of(rows), "Lhs len must be a multiple of rows");
+        let inner = lhs.len() / rows;
+        assert_eq!(rhs.len(), inner * cols, "Rhs len must be inner × cols");
+
+        let mut product = Vec::with_capacity(rows * cols);
+        for r in 0..rows {
+            for c in 0..cols {
+                product.push(Self::sum(&(0..inner).map(|k| lhs[r * inner + k] * rhs[k * cols + c]).collect::<Vec<Self>>()));
+            }
+        }
+
+        product
+    }
+
+    #[inline]
+    fn matmul_real(lhs: &[Self], rhs: &[T], rows: usize, cols: usize) -> Vec<Self> {
+        Self::matmul(lhs, &rhs.iter().map(|&x| Self::point(x)).collect::<Vec<Self>>(), rows, cols)
+    }
+
+    #[inline]
+    fn broadcast_add(lhs: &[Self], rhs: &[Self]) -> Vec<Self> {
+        assert!(!rhs.is_empty() && lhs.len().is_multiple_of(rhs.len()), "Lhs len must be a multiple of rhs len");
+        lhs.iter().enumerate().map(|(i, &x)| x + rhs[i % rhs.len()]).collect()
+    }
+
+    #[inline]
+    fn sum(values: &[Self]) -> Self {
+        values.iter().fold(Self::point(T::zero()), |acc, &x| acc + x)
+    }
+
+    #[inline]
+    fn dot(values: &[Self], coefficients: &[T]) -> Self {
+        assert_eq!(values.len(), coefficients.len(), "Values and coefficients must have the same len");
+        Self::sum(&values.iter().zip(coefficients).map(|(&x, &c)| x * c).collect::<Vec<Self>>())
+    }
+
+    #[inline]
+    fn mean(values: &[Self]) -> Self {
+        Self::sum(values) / T::from_f64(values.len() as f64)
+    }
+}
+
+impl<T: Real> Activate<T> for Interval<T> {
+    /// Monotonic activation functions are applied to the bounds directly, which gives the tightest interval.
+    #[inline]
+    fn activate(values: &[Self], activation_fn: ActivationFn) -> Vec<Self> {
+        match activation_fn {
+            ActivationFn::GELU | ActivationFn::SiLU => values.iter().map(|&x| activation_fn.compute(x)).collect(),
+            _ => values.iter().map(|x| x.increasing(|x| activation_fn.compute(x))).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks that `f` of evenly spaced points in `x` lands inside `f(x)`.
+    fn assert_encloses(x: Interval<f64>, f: impl Fn(Interval<f64>) -> Interval<f64>) {
+        let bounds = f(x);
+        for i in 0..=100 {
+            let point = (x.lo + x.width() * i as f64 / 100.0).min(x.hi);
+            let value = f(Interval::point(point));
+            assert!(bounds.lo <= value.lo && value.hi <= bounds.hi, "{:?} does not contain {:?} at {}", bounds, value, point);
+        }
+    }
+
+    #[test]
+    fn arithmetic_test() {
+        let x = Interval::new(-1.0, 2.0);
+        let y = Interval::new(0.5, 3.0);
+
+        assert_eq!(-x, Interval::new(-2.0, 1.0));
+        assert!((x * y).contains(-3.0) && (x * y).contains(6.0) && !(x * y).contains(6.001));
+        assert!((x / y).contains(-2.0) && (x / y).contains(4.0) && !(x / y).contains(-2.001));
+        assert_eq!(x / x, Interval::everything());
+        assert_eq!(x.abs(), Interval::new(0.0, 2.0));
+        assert_eq!(x.square().lo, 0.0);
+        assert_eq!(Interval::new(-3.0, -2.0).powi(3).hi, -8.0 * (1.0 - f64::EPSILON));
+        assert!(Interval::point(1.0).powi(i32::MIN).contains(1.0) && Interval::point(2.0).powi(i32::MIN).contains(0.0));
+        assert!((x % y).contains(-1.0) && (x % y).contains(2.0));
+
+        let functions: [fn(Interval<f64>) -> Interval<f64>; 12] = [
+            |x| x.sin(), |x| x.cos(), |x| x.exp(), |x| x.cosh(), |x| x.tanh(), |x| x.square(), |x| x.powi(-2),
+            |x| x.hypot(x * 0.5), |x| x.powi(3) - x, |x| x * x, |x| x.atan2(Interval::point(2.0)), |x| (x - 3.0).recip(),
+        ];
+        for f in functions {
+            assert_encloses(Interval::new(-1.3, 2.1), f);
+            assert_encloses(Interval::new(0.2, 0.3), f);
+            assert_encloses(Interval::new(-7.0, -4.0), f);
+        }
+    }
+
+    #[test]
+    fn periodic_test() {
+        let pi = std::f64::consts::PI;
+
+        let sin = Interval::new(0.0, pi).sin();
+        assert!(sin.contains(1.0) && sin.lo < 1E-15 && sin.lo > -1E-15);
+        assert_eq!(Interval::new(0.0, 7.0).sin(), Interval::new(-1.0, 1.0));
+        assert_eq!(Interval::new(pi, 2.0 * pi).cos().lo, -1.0);
+        assert_eq!(Interval::new(1.0, 2.0).tan(), Interval::everything());
+        assert!(Interval::new(-1.0, 1.0).tan().contains(1f64.tan()));
+    }
+
+    #[test]
+    fn activate_test() {
+        let x = [Interval::new(-1.5, 0.5), Interval::new(-0.2, 3.0)];
+        for activation_fn in [ActivationFn::ReLU, ActivationFn::Sigmoid, ActivationFn::GELU, ActivationFn::SiLU, ActivationFn::SmoothReLU] {
+            let bounds = Interval::activate(&x, activation_fn);
+            for (x, bounds) in x.iter().zip(&bounds) {
+                for i in 0..=100 {
+                    let value = activation_fn.compute::<f64, f64>((x.lo + x.width() * i as f64 / 100.0).min(x.hi));
+                    assert!(bounds.contains(value), "{:?} {:?} does not contain {}", activation_fn, bounds, value);
+                }
+            }
+        }
+
+        assert_eq!(Interval::activate(&x, ActivationFn::ReLU)[0].lo, 0.0);
+    }
+}
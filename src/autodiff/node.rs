@@ -1,7 +1,230 @@
+use serde::Serialize;
+
 use super::real::Real;
 
-#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
-pub struct Node<T: Real> {
-    pub(super) partials: [T; 2],
-    pub(super) parents: [usize; 2],
-}
\ No newline at end of file
+/// A single operation recorded on a `Tape`. Writes the values of the tape slots starting at `index`.
+#[derive(Clone, Debug, PartialEq, PartialOrd, Serialize)]
+pub(super) struct Node<T: Real> {
+    pub(super) index: usize,
+    pub(super) name: &'static str,
+    pub(super) kind: NodeKind<T>,
+}
+
+#[derive(Clone, Debug, PartialEq, PartialOrd, Serialize)]
+pub(super) enum NodeKind<T: Real> {
+    /// An independent variable. Has no parents.
+    Input,
+    /// A value that gradients do not flow through. Has no parents.
+    Constant,
+    /// A scalar operation with up to two parents. For unary operations, the right partial is zero.
+    Scalar {
+        partials: [T; 2],
+        parents: [usize; 2],
+    },
+    /// A row-major `rows`×`inner` matrix times an `inner`×`cols` matrix.
+    MatMul {
+        lhs: Indices,
+        rhs: Operand<T>,
+        rows: usize,
+        inner: usize,
+        cols: usize,
+    },
+    /// Elementwise addition, with `rhs` repeated over the length of `lhs`.
+    BroadcastAdd {
+        lhs: Indices,
+        rhs: Indices,
+    },
+    /// An elementwise function, storing the derivative at each element.
+    Map {
+        input: Indices,
+        partials: Vec<T>,
+    },
+    /// An operation with any number of parents, storing the partial with respect to each.
+    Nary {
+        parents: Indices,
+        partials: Vec<T>,
+    },
+    /// The sum of all of `input`.
+    Sum {
+        input: Indices,
+    },
+    /// The mean of all of `input`.
+    Mean {
+        input: Indices,
+        len_recip: T,
+    },
+}
+
+/// The tape slots read by a `Node`. Slices created together (such as params) are stored as a range.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub(super) enum Indices {
+    Range(usize, usize),
+    List(Vec<usize>),
+}
+
+impl Indices {
+    #[inline]
+    pub(super) fn new(indices: impl ExactSizeIterator<Item = usize> + Clone) -> Self {
+        let len = indices.len();
+        let mut iter = indices.clone();
+        let start = iter.next().unwrap_or(0);
+        if iter.enumerate().all(|(i, idx)| idx == start + i + 1) {
+            Indices::Range(start, len)
+        }
+        else {
+            Indices::List(indices.collect())
+        }
+    }
+
+    #[inline]
+    pub(super) fn len(&self) -> usize {
+        match self {
+            Indices::Range(_, len) => *len,
+            Indices::List(list) => list.len(),
+        }
+    }
+
+    #[inline]
+    pub(super) fn get(&self, i: usize) -> usize {
+        match self {
+            Indices::Range(start, _) => start + i,
+            Indices::List(list) => list[i],
+        }
+    }
+}
+
+/// The right hand side of a `MatMul`, which is either on the tape or a constant.
+#[derive(Clone, Debug, PartialEq, PartialOrd, Serialize)]
+pub(super) enum Operand<T: Real> {
+    Var(Indices),
+    Const(Vec<T>),
+}
+
+impl<T: Real> NodeKind<T> {
+    /// The name a node of this kind is given when it is recorded.
+    #[inline]
+    pub(super) fn default_name(&self) -> &'static str {
+        match self {
+            NodeKind::Input => "input",
+            NodeKind::Constant => "constant",
+            NodeKind::Scalar { .. } => "scalar",
+            NodeKind::MatMul { .. } => "matmul",
+            NodeKind::BroadcastAdd { .. } => "broadcast_add",
+            NodeKind::Map { .. } => "map",
+            NodeKind::Nary { .. } => "nary",
+            NodeKind::Sum { .. } => "sum",
+            NodeKind::Mean { .. } => "mean",
+        }
+    }
+
+    /// Returns the tape slots read by this node, along with the partial with respect to each if it is stored.
+    #[inline]
+    pub(super) fn parents(&self) -> Vec<(usize, Option<T>)> {
+        match self {
+            NodeKind::Input | NodeKind::Constant => vec![],
+            NodeKind::Scalar { partials, parents } => {
+                // unary operations point their unused parent at the same slot with a zero partial
+                if parents[0] == parents[1] && partials[1] == T::zero() {
+                    vec![(parents[0], Some(partials[0]))]
+                }
+                else {
+                    vec![(parents[0], Some(partials[0])), (parents[1], Some(partials[1]))]
+                }
+            },
+            NodeKind::MatMul { lhs, rhs, .. } => {
+                let mut parents: Vec<(usize, Option<T>)> = (0..lhs.len()).map(|i| (lhs.get(i), None)).collect();
+                if let Operand::Var(rhs) = rhs {
+                    parents.extend((0..rhs.len()).map(|i| (rhs.get(i), None)));
+                }
+
+                parents
+            },
+            NodeKind::BroadcastAdd { lhs, rhs } => {
+                (0..lhs.len()).map(|i| (lhs.get(i), Some(T::one()))).chain((0..rhs.len()).map(|i| (rhs.get(i), Some(T::one())))).collect()
+            },
+            NodeKind::Map { input, partials } | NodeKind::Nary { parents: input, partials } => {
+                (0..input.len()).map(|i| (input.get(i), Some(partials[i]))).collect()
+            },
+            NodeKind::Sum { input } => (0..input.len()).map(|i| (input.get(i), Some(T::one()))).collect(),
+            NodeKind::Mean { input, len_recip } => (0..input.len()).map(|i| (input.get(i), Some(*len_recip))).collect(),
+        }
+    }
+}
+
+impl<T: Real> Operand<T> {
+    #[inline]
+    fn val(&self, values: &[T], i: usize) -> T {
+        match self {
+            Operand::Var(indices) => values[indices.get(i)],
+            Operand::Const(vals) => vals[i],
+        }
+    }
+}
+
+impl<T: Real> Node<T> {
+    /// Adds the contribution of this node's output gradients to the gradients of its parents.
+    #[inline]
+    pub(super) fn backward(&self, values: &[T], grad: &mut [T]) {
+        let index = self.index;
+        match &self.kind {
+            NodeKind::Input | NodeKind::Constant => (),
+            NodeKind::Scalar { partials, parents } => {
+                let grad_i = grad[index];
+                grad[parents[0]] = grad[parents[0]] + partials[0] * grad_i;
+                grad[parents[1]] = grad[parents[1]] + partials[1] * grad_i;
+            },
+            NodeKind::MatMul { lhs, rhs, rows, inner, cols } => {
+                for r in 0..*rows {
+                    for c in 0..*cols {
+                        let grad_out = grad[index + r * cols + c];
+                        for k in 0..*inner {
+                            let l = lhs.get(r * inner + k);
+                            grad[l] = grad[l] + rhs.val(values, k * cols + c) * grad_out;
+
+                            if let Operand::Var(rhs) = rhs {
+                                let rh = rhs.get(k * cols + c);
+                                grad[rh] = grad[rh] + values[l] * grad_out;
+                            }
+                        }
+                    }
+                }
+            },
+            NodeKind::BroadcastAdd { lhs, rhs } => {
+                for i in 0..lhs.len() {
+                    let grad_out = grad[index + i];
+                    let l = lhs.get(i);
+                    let r = rhs.get(i % rhs.len());
+                    grad[l] = grad[l] + grad_out;
+                    grad[r] = grad[r] + grad_out;
+                }
+            },
+            NodeKind::Map { input, partials } => {
+                for (i, partial) in partials.iter().enumerate() {
+                    let x = input.get(i);
+                    grad[x] = grad[x] + *partial * grad[index + i];
+                }
+            },
+            NodeKind::Nary { parents, partials } => {
+                let grad_out = grad[index];
+                for (i, partial) in partials.iter().enumerate() {
+                    let parent = parents.get(i);
+                    grad[parent] = grad[parent] + *partial * grad_out;
+                }
+            },
+            NodeKind::Sum { input } => {
+                let grad_out = grad[index];
+                for i in 0..input.len() {
+                    let x = input.get(i);
+                    grad[x] = grad[x] + grad_out;
+                }
+            },
+            NodeKind::Mean { input, len_recip } => {
+                let grad_out = *len_recip * grad[index];
+                for i in 0..input.len() {
+                    let x = input.get(i);
+                    grad[x] = grad[x] + grad_out;
+                }
+            },
+        }
+    }
+}
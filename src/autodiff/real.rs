@@ -1,21 +1,89 @@
+mod emulated;
 pub mod f32;
 pub mod f64;
+pub mod fixed;
 pub mod operations;
 pub mod real_math;
+pub mod soft_float;
 
 use std::fmt::Debug;
 
-use operations::{Clamp, OperateWithReal};
+use crate::rng::i64_to_real;
+
+use operations::{Clamp, OperateWithReal, TensorOperations};
 use real_math::RealMath;
 
-pub trait Real: RealMath + Clamp + Debug + PartialEq + PartialOrd
+pub trait Real: RealMath + Clamp + Debug + PartialEq + PartialOrd + Send + Sync
 {
     const MIN: Self;
     const MAX: Self;
+    /// The difference between `1.0` and the next larger representable number.
+    const EPSILON: Self;
+    /// Whether `Self` is emulated in software with too little range for the random number generator,
+    /// which then runs in `f64` instead.
+    const EMULATED: bool = false;
 
     fn zero() -> Self;
     fn one() -> Self;
+    /// Returns `true` if `self` is neither infinite nor NaN.
+    fn is_finite(self) -> bool;
+    /// Converts `x` to the nearest representable value.
+    fn from_f64(x: f64) -> Self;
+    fn to_f64(self) -> f64;
 }
 
 impl<T: Real> OperateWithReal<T> for T {
-}
\ No newline at end of file
+}
+impl<T: Real> TensorOperations<T> for T {
+    #[inline]
+    fn matmul(lhs: &[Self], rhs: &[Self], rows: usize, cols: usize) -> Vec<Self> {
+        assert!(rows > 0 && cols > 0 && lhs.len().is_multiple_of(rows), "Lhs len must be a multiple of rows");
+        let inner = lhs.len() / rows;
+        assert_eq!(inner * cols, rhs.len(), "Rhs len must be lhs cols times rhs cols");
+
+        let mut output = Vec::with_capacity(rows * cols);
+        for r in 0..rows {
+            for c in 0..cols {
+                let mut sum = T::zero();
+                for k in 0..inner {
+                    sum = sum + lhs[r * inner + k] * rhs[k * cols + c];
+                }
+
+                output.push(sum);
+            }
+        }
+
+        output
+    }
+
+    #[inline]
+    fn matmul_real(lhs: &[Self], rhs: &[T], rows: usize, cols: usize) -> Vec<Self> {
+        Self::matmul(lhs, rhs, rows, cols)
+    }
+
+    #[inline]
+    fn broadcast_add(lhs: &[Self], rhs: &[Self]) -> Vec<Self> {
+        assert!(!rhs.is_empty() && lhs.len().is_multiple_of(rhs.len()), "Rhs len must divide lhs len");
+
+        (0..lhs.len()).map(|i| lhs[i] + rhs[i % rhs.len()]).collect()
+    }
+
+    #[inline]
+    fn sum(values: &[Self]) -> Self {
+        values.iter().fold(T::zero(), |acc, &x| acc + x)
+    }
+
+    #[inline]
+    fn dot(values: &[Self], coefficients: &[T]) -> Self {
+        assert_eq!(values.len(), coefficients.len(), "Values and coefficients must have same len");
+
+        values.iter().zip(coefficients).fold(T::zero(), |acc, (&x, &c)| acc + x * c)
+    }
+
+    #[inline]
+    fn mean(values: &[Self]) -> Self {
+        if values.is_empty() { panic!("Cannot take the mean of no values") };
+
+        Self::sum(values) / i64_to_real(values.len() as i64)
+    }
+}
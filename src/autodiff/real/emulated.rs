@@ -0,0 +1,137 @@
+//! Helpers for `Real` types that are emulated in software. Each operation is computed in `f64` and rounded back,
+//! which for the basic arithmetic operations gives the correctly rounded result.
+
+/// Implements `UnaryOperations`, `BinaryOperations` and `Clamp` for a `Real` type by way of `f64`.
+macro_rules! impl_math_via_f64 {
+    ($t:ty $(, const $c:ident: $ct:ty)?) => {
+        impl$(<const $c: $ct>)? $crate::autodiff::real::operations::UnaryOperations for $t {
+            #[inline] fn recip(self) -> Self { Self::from_f64(self.to_f64().recip()) }
+            #[inline] fn abs(self) -> Self { Self::from_f64(self.to_f64().abs()) }
+            #[inline] fn signum(self) -> Self { Self::from_f64(self.to_f64().signum()) }
+            #[inline] fn sqrt(self) -> Self { Self::from_f64(self.to_f64().sqrt()) }
+            #[inline] fn exp(self) -> Self { Self::from_f64(self.to_f64().exp()) }
+            #[inline] fn exp2(self) -> Self { Self::from_f64(self.to_f64().exp2()) }
+            #[inline] fn ln(self) -> Self { Self::from_f64(self.to_f64().ln()) }
+            #[inline] fn log2(self) -> Self { Self::from_f64(self.to_f64().log2()) }
+            #[inline] fn log10(self) -> Self { Self::from_f64(self.to_f64().log10()) }
+            #[inline] fn cbrt(self) -> Self { Self::from_f64(self.to_f64().cbrt()) }
+            #[inline] fn sin(self) -> Self { Self::from_f64(self.to_f64().sin()) }
+            #[inline] fn cos(self) -> Self { Self::from_f64(self.to_f64().cos()) }
+            #[inline] fn tan(self) -> Self { Self::from_f64(self.to_f64().tan()) }
+            #[inline] fn asin(self) -> Self { Self::from_f64(self.to_f64().asin()) }
+            #[inline] fn acos(self) -> Self { Self::from_f64(self.to_f64().acos()) }
+            #[inline] fn atan(self) -> Self { Self::from_f64(self.to_f64().atan()) }
+            #[inline] fn exp_m1(self) -> Self { Self::from_f64(self.to_f64().exp_m1()) }
+            #[inline] fn ln_1p(self) -> Self { Self::from_f64(self.to_f64().ln_1p()) }
+            #[inline] fn sinh(self) -> Self { Self::from_f64(self.to_f64().sinh()) }
+            #[inline] fn cosh(self) -> Self { Self::from_f64(self.to_f64().cosh()) }
+            #[inline] fn tanh(self) -> Self { Self::from_f64(self.to_f64().tanh()) }
+            #[inline] fn asinh(self) -> Self { Self::from_f64(self.to_f64().asinh()) }
+            #[inline] fn acosh(self) -> Self { Self::from_f64(self.to_f64().acosh()) }
+            #[inline] fn atanh(self) -> Self { Self::from_f64(self.to_f64().atanh()) }
+            #[inline] fn trunc(self) -> Self { Self::from_f64(self.to_f64().trunc()) }
+            #[inline] fn floor(self) -> Self { Self::from_f64(self.to_f64().floor()) }
+            #[inline] fn ceil(self) -> Self { Self::from_f64(self.to_f64().ceil()) }
+            #[inline] fn round(self) -> Self { Self::from_f64(self.to_f64().round()) }
+            #[inline] fn square(self) -> Self { self * self }
+            #[inline] fn powi(self, n: i32) -> Self { Self::from_f64(self.to_f64().powi(n)) }
+        }
+
+        impl$(<const $c: $ct>)? $crate::autodiff::real::operations::BinaryOperations for $t {
+            #[inline] fn log(self, base: Self) -> Self { Self::from_f64(self.to_f64().log(base.to_f64())) }
+            #[inline] fn powf(self, n: Self) -> Self { Self::from_f64(self.to_f64().powf(n.to_f64())) }
+            #[inline] fn min(self, other: Self) -> Self { Self::from_f64(self.to_f64().min(other.to_f64())) }
+            #[inline] fn max(self, other: Self) -> Self { Self::from_f64(self.to_f64().max(other.to_f64())) }
+            #[inline] fn hypot(self, other: Self) -> Self { Self::from_f64(self.to_f64().hypot(other.to_f64())) }
+            #[inline] fn atan2(self, other: Self) -> Self { Self::from_f64(self.to_f64().atan2(other.to_f64())) }
+            #[inline] fn mul_add(self, a: Self, b: Self) -> Self { Self::from_f64(self.to_f64().mul_add(a.to_f64(), b.to_f64())) }
+        }
+
+        impl$(<const $c: $ct>)? $crate::autodiff::real::operations::Clamp for $t {
+            #[inline]
+            fn clamp(self, min: Self, max: Self) -> Self {
+                Self::from_f64(self.to_f64().clamp(min.to_f64(), max.to_f64()))
+            }
+        }
+
+        impl$(<const $c: $ct>)? From<$t> for f64 {
+            #[inline]
+            fn from(x: $t) -> f64 {
+                x.to_f64()
+            }
+        }
+
+        impl$(<const $c: $ct>)? From<f64> for $t {
+            #[inline]
+            fn from(x: f64) -> Self {
+                Self::from_f64(x)
+            }
+        }
+
+        impl$(<const $c: $ct>)? std::fmt::Debug for $t {
+            #[inline]
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                std::fmt::Debug::fmt(&self.to_f64(), f)
+            }
+        }
+
+        impl$(<const $c: $ct>)? std::fmt::Display for $t {
+            #[inline]
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                std::fmt::Display::fmt(&self.to_f64(), f)
+            }
+        }
+    };
+}
+
+/// Implements the arithmetic operators and comparisons for a `Real` type by way of `f64`.
+macro_rules! impl_arithmetic_via_f64 {
+    ($t:ty) => {
+        impl std::ops::Neg for $t {
+            type Output = Self;
+            #[inline] fn neg(self) -> Self { Self::from_f64(-self.to_f64()) }
+        }
+
+        impl std::ops::Add for $t {
+            type Output = Self;
+            #[inline] fn add(self, rhs: Self) -> Self { Self::from_f64(self.to_f64() + rhs.to_f64()) }
+        }
+
+        impl std::ops::Sub for $t {
+            type Output = Self;
+            #[inline] fn sub(self, rhs: Self) -> Self { Self::from_f64(self.to_f64() - rhs.to_f64()) }
+        }
+
+        impl std::ops::Mul for $t {
+            type Output = Self;
+            #[inline] fn mul(self, rhs: Self) -> Self { Self::from_f64(self.to_f64() * rhs.to_f64()) }
+        }
+
+        impl std::ops::Div for $t {
+            type Output = Self;
+            #[inline] fn div(self, rhs: Self) -> Self { Self::from_f64(self.to_f64() / rhs.to_f64()) }
+        }
+
+        impl std::ops::Rem for $t {
+            type Output = Self;
+            #[inline] fn rem(self, rhs: Self) -> Self { Self::from_f64(self.to_f64() % rhs.to_f64()) }
+        }
+
+        impl PartialEq for $t {
+            #[inline]
+            fn eq(&self, other: &Self) -> bool {
+                self.to_f64() == other.to_f64()
+            }
+        }
+
+        impl PartialOrd for $t {
+            #[inline]
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                self.to_f64().partial_cmp(&other.to_f64())
+            }
+        }
+    };
+}
+
+pub(crate) use impl_arithmetic_via_f64;
+pub(crate) use impl_math_via_f64;
@@ -6,6 +6,8 @@ impl Real for f32 {
     const MIN: Self = f32::MIN;
     
     const MAX: Self = f32::MAX;
+    
+    const EPSILON: Self = f32::EPSILON;
 
     #[inline]
     fn zero() -> Self {
@@ -16,6 +18,21 @@ impl Real for f32 {
     fn one() -> Self {
         1f32
     }
+
+    #[inline]
+    fn is_finite(self) -> bool {
+        self.is_finite()
+    }
+
+    #[inline]
+    fn from_f64(x: f64) -> Self {
+        x as f32
+    }
+
+    #[inline]
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
 }
 
 impl UnaryOperations for f32 {
@@ -158,6 +175,16 @@ impl UnaryOperations for f32 {
     fn round(self) -> Self {
         self.round()
     }
+
+    #[inline]
+    fn square(self) -> Self {
+        self * self
+    }
+
+    #[inline]
+    fn powi(self, n: i32) -> Self {
+        self.powi(n)
+    }
 }
 
 impl BinaryOperations for f32 {
@@ -170,6 +197,31 @@ impl BinaryOperations for f32 {
     fn powf(self, n: Self) -> Self {
         self.powf(n)
     }
+
+    #[inline]
+    fn min(self, other: Self) -> Self {
+        self.min(other)
+    }
+
+    #[inline]
+    fn max(self, other: Self) -> Self {
+        self.max(other)
+    }
+
+    #[inline]
+    fn hypot(self, other: Self) -> Self {
+        self.hypot(other)
+    }
+
+    #[inline]
+    fn atan2(self, other: Self) -> Self {
+        self.atan2(other)
+    }
+
+    #[inline]
+    fn mul_add(self, a: Self, b: Self) -> Self {
+        self.mul_add(a, b)
+    }
 }
 
 impl Clamp for f32 {
@@ -0,0 +1,165 @@
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
+
+use bitcode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+use crate::autodiff::real::Real;
+
+use super::emulated::impl_math_via_f64;
+
+/// A signed 32 bit fixed-point number with `FRAC_BITS` fractional bits. Arithmetic is done on the underlying integer
+/// and saturates instead of overflowing, while other functions are computed in `f64` and rounded back.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Encode, Decode)]
+#[serde(into = "f64", from = "f64")]
+pub struct Fixed<const FRAC_BITS: u32>(i32);
+
+/// A fixed-point number with 16 integer bits and 16 fractional bits.
+pub type Q16_16 = Fixed<16>;
+
+impl<const FRAC_BITS: u32> Fixed<FRAC_BITS> {
+    #[inline]
+    pub const fn from_bits(bits: i32) -> Self {
+        Fixed(bits)
+    }
+
+    #[inline]
+    pub const fn to_bits(self) -> i32 {
+        self.0
+    }
+
+    #[inline]
+    fn saturate(x: i64) -> Self {
+        Fixed(x.clamp(i32::MIN as i64, i32::MAX as i64) as i32)
+    }
+}
+
+impl<const FRAC_BITS: u32> Real for Fixed<FRAC_BITS> {
+    const MIN: Self = Fixed(i32::MIN);
+
+    const MAX: Self = Fixed(i32::MAX);
+
+    const EPSILON: Self = Fixed(1);
+
+    const EMULATED: bool = true;
+
+    #[inline]
+    fn zero() -> Self {
+        Fixed(0)
+    }
+
+    #[inline]
+    fn one() -> Self {
+        const { assert!(FRAC_BITS < 31, "Fixed needs an integer bit to represent one") };
+        Fixed(1 << FRAC_BITS)
+    }
+
+    #[inline]
+    fn is_finite(self) -> bool {
+        true
+    }
+
+    /// Rounds `x` to the nearest multiple of `EPSILON`, saturating at `MIN` and `MAX`. NaN becomes zero.
+    #[inline]
+    fn from_f64(x: f64) -> Self {
+        Fixed((x * (1u64 << FRAC_BITS) as f64).round() as i32)
+    }
+
+    #[inline]
+    fn to_f64(self) -> f64 {
+        self.0 as f64 / (1u64 << FRAC_BITS) as f64
+    }
+}
+
+impl<const FRAC_BITS: u32> Neg for Fixed<FRAC_BITS> {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self::Output {
+        Fixed(self.0.saturating_neg())
+    }
+}
+
+impl<const FRAC_BITS: u32> Add for Fixed<FRAC_BITS> {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self::Output {
+        Fixed(self.0.saturating_add(rhs.0))
+    }
+}
+
+impl<const FRAC_BITS: u32> Sub for Fixed<FRAC_BITS> {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self::Output {
+        Fixed(self.0.saturating_sub(rhs.0))
+    }
+}
+
+impl<const FRAC_BITS: u32> Mul for Fixed<FRAC_BITS> {
+    type Output = Self;
+
+    /// Rounds the product to the nearest multiple of `EPSILON`.
+    #[inline]
+    fn mul(self, rhs: Self) -> Self::Output {
+        let product = self.0 as i64 * rhs.0 as i64;
+        Self::saturate((product + (1 << FRAC_BITS >> 1)) >> FRAC_BITS)
+    }
+}
+
+impl<const FRAC_BITS: u32> Div for Fixed<FRAC_BITS> {
+    type Output = Self;
+
+    /// Truncates the quotient towards zero. Dividing by zero saturates in the direction of `self`.
+    #[inline]
+    fn div(self, rhs: Self) -> Self::Output {
+        if rhs.0 == 0 {
+            return if self.0 >= 0 { Self::MAX } else { Self::MIN };
+        }
+
+        Self::saturate(((self.0 as i64) << FRAC_BITS) / rhs.0 as i64)
+    }
+}
+
+impl<const FRAC_BITS: u32> Rem for Fixed<FRAC_BITS> {
+    type Output = Self;
+
+    /// The remainder with a zero divisor is zero.
+    #[inline]
+    fn rem(self, rhs: Self) -> Self::Output {
+        Fixed(self.0.checked_rem(rhs.0).unwrap_or(0))
+    }
+}
+
+impl_math_via_f64!(Fixed<FRAC_BITS>, const FRAC_BITS: u32);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autodiff::real::operations::UnaryOperations;
+
+    #[test]
+    fn fixed_test() {
+        let x = Q16_16::from_f64(1.5);
+        let y = Q16_16::from_f64(-0.25);
+
+        assert_eq!(Q16_16::one().to_bits(), 65536);
+        assert_eq!(Q16_16::EPSILON.to_f64(), 2f64.powi(-16));
+        assert_eq!((x + y).to_f64(), 1.25);
+        assert_eq!((x * y).to_f64(), -0.375);
+        assert_eq!((x / y).to_f64(), -6.0);
+        assert_eq!((x % Q16_16::from_f64(0.4)).to_bits(), 98304 - 3 * 26214);
+        assert_eq!(Q16_16::from_f64(0.1).to_bits(), 6554);
+        assert_eq!(Q16_16::from_f64(1E9), Q16_16::MAX);
+        assert_eq!(Q16_16::MAX + x, Q16_16::MAX);
+        assert_eq!(Q16_16::MIN * x, Q16_16::MIN);
+        assert_eq!(x / Q16_16::zero(), Q16_16::MAX);
+        assert_eq!(x.sqrt().to_f64(), Q16_16::from_f64(1.5f64.sqrt()).to_f64());
+        assert_eq!(format!("{:?}", y), "-0.25");
+
+        // fewer fractional bits trade precision for range
+        assert_eq!(Fixed::<8>::from_f64(1E6).to_f64(), 1E6);
+        assert_eq!(Fixed::<8>::from_f64(0.1).to_f64(), 0.1015625);
+    }
+}
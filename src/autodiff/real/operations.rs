@@ -31,11 +31,20 @@ pub trait UnaryOperations {
     fn floor(self) -> Self;
     fn ceil(self) -> Self;
     fn round(self) -> Self;
+    fn square(self) -> Self;
+    fn powi(self, n: i32) -> Self;
 }
 
 pub trait BinaryOperations<Rhs = Self, Output = Self> {
     fn log(self, base: Rhs) -> Output;
     fn powf(self, n: Rhs) -> Output;
+    fn min(self, other: Rhs) -> Output;
+    fn max(self, other: Rhs) -> Output;
+    fn hypot(self, other: Rhs) -> Output;
+    /// Computes the four quadrant arctangent of `self` (y) and `other` (x).
+    fn atan2(self, other: Rhs) -> Output;
+    /// Computes `self * a + b`.
+    fn mul_add(self, a: Rhs, b: Rhs) -> Output;
 }
 
 pub trait Clamp<MinMax = Self> {
@@ -45,4 +54,17 @@ pub trait Clamp<MinMax = Self> {
 pub trait OperateWithReal<T: Real, Output = Self>: 
     Sized + BinaryOperations<T> + 
     Add<T, Output = Output> + Sub<T, Output = Output> + Mul<T, Output = Output> + Div<T, Output = Output> + Rem<T, Output = Output> {
-}
\ No newline at end of file
+}
+/// Operations over whole slices. On a `Tape`, each of these is recorded as a single node.
+pub trait TensorOperations<T: Real>: Sized {
+    /// Multiplies the row-major `rows`×`inner` matrix `lhs` by the `inner`×`cols` matrix `rhs`.
+    fn matmul(lhs: &[Self], rhs: &[Self], rows: usize, cols: usize) -> Vec<Self>;
+    /// Same as `matmul`, but with a constant `rhs`.
+    fn matmul_real(lhs: &[Self], rhs: &[T], rows: usize, cols: usize) -> Vec<Self>;
+    /// Adds `rhs` to `lhs` elementwise, repeating `rhs` if it is shorter than `lhs`.
+    fn broadcast_add(lhs: &[Self], rhs: &[Self]) -> Vec<Self>;
+    fn sum(values: &[Self]) -> Self;
+    /// Computes the dot product of `values` and the constant `coefficients`.
+    fn dot(values: &[Self], coefficients: &[T]) -> Self;
+    fn mean(values: &[Self]) -> Self;
+}
@@ -0,0 +1,212 @@
+use bitcode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+use crate::autodiff::real::Real;
+
+use super::emulated::{impl_arithmetic_via_f64, impl_math_via_f64};
+
+/// A software bfloat16: 1 sign bit, 8 exponent bits and 7 mantissa bits. Has the range of an `f32` with much less precision.
+#[derive(Clone, Copy, Default, Serialize, Deserialize, Encode, Decode)]
+#[serde(into = "f64", from = "f64")]
+pub struct Bf16(u16);
+
+/// A software IEEE 754 half precision float: 1 sign bit, 5 exponent bits and 10 mantissa bits.
+#[derive(Clone, Copy, Default, Serialize, Deserialize, Encode, Decode)]
+#[serde(into = "f64", from = "f64")]
+pub struct F16(u16);
+
+impl Bf16 {
+    const EXP_BITS: u32 = 8;
+    const MAN_BITS: u32 = 7;
+
+    #[inline]
+    pub const fn from_bits(bits: u16) -> Self {
+        Bf16(bits)
+    }
+
+    #[inline]
+    pub const fn to_bits(self) -> u16 {
+        self.0
+    }
+}
+
+impl F16 {
+    const EXP_BITS: u32 = 5;
+    const MAN_BITS: u32 = 10;
+
+    #[inline]
+    pub const fn from_bits(bits: u16) -> Self {
+        F16(bits)
+    }
+
+    #[inline]
+    pub const fn to_bits(self) -> u16 {
+        self.0
+    }
+}
+
+impl Real for Bf16 {
+    const MIN: Self = Bf16(0xFF7F);
+
+    const MAX: Self = Bf16(0x7F7F);
+
+    const EPSILON: Self = Bf16(0x3C00);
+
+    const EMULATED: bool = true;
+
+    #[inline]
+    fn zero() -> Self {
+        Bf16(0)
+    }
+
+    #[inline]
+    fn one() -> Self {
+        Bf16(0x3F80)
+    }
+
+    #[inline]
+    fn is_finite(self) -> bool {
+        self.0 & 0x7F80 != 0x7F80
+    }
+
+    #[inline]
+    fn from_f64(x: f64) -> Self {
+        Bf16(encode(x, Self::EXP_BITS, Self::MAN_BITS))
+    }
+
+    #[inline]
+    fn to_f64(self) -> f64 {
+        decode(self.0, Self::EXP_BITS, Self::MAN_BITS)
+    }
+}
+
+impl Real for F16 {
+    const MIN: Self = F16(0xFBFF);
+
+    const MAX: Self = F16(0x7BFF);
+
+    const EPSILON: Self = F16(0x1400);
+
+    const EMULATED: bool = true;
+
+    #[inline]
+    fn zero() -> Self {
+        F16(0)
+    }
+
+    #[inline]
+    fn one() -> Self {
+        F16(0x3C00)
+    }
+
+    #[inline]
+    fn is_finite(self) -> bool {
+        self.0 & 0x7C00 != 0x7C00
+    }
+
+    #[inline]
+    fn from_f64(x: f64) -> Self {
+        F16(encode(x, Self::EXP_BITS, Self::MAN_BITS))
+    }
+
+    #[inline]
+    fn to_f64(self) -> f64 {
+        decode(self.0, Self::EXP_BITS, Self::MAN_BITS)
+    }
+}
+
+impl_math_via_f64!(Bf16);
+impl_arithmetic_via_f64!(Bf16);
+impl_math_via_f64!(F16);
+impl_arithmetic_via_f64!(F16);
+
+/// Rounds `x` to the nearest 16 bit float with the given number of exponent and mantissa bits, with ties to even.
+#[inline]
+fn encode(x: f64, exp_bits: u32, man_bits: u32) -> u16 {
+    let sign = if x.is_sign_negative() { 1 << 15 } else { 0 };
+    let exp_mask = (1 << exp_bits) - 1;
+    if x.is_nan() {
+        return sign | exp_mask << man_bits | 1 << (man_bits - 1);
+    }
+
+    let abs = x.abs();
+    if abs == 0.0 {
+        return sign;
+    }
+
+    // values below the smallest normal exponent are subnormal and share its spacing
+    let bias = (1 << (exp_bits - 1)) - 1;
+    let exp = (((abs.to_bits() >> 52) & 0x7FF) as i32 - 1023).max(1 - bias);
+    let mut mantissa = (abs / 2f64.powi(exp - man_bits as i32)).round_ties_even() as u32;
+    let mut biased_exp = exp + bias;
+
+    if mantissa >= 1 << (man_bits + 1) {
+        // rounding carried into the next exponent
+        mantissa >>= 1;
+        biased_exp += 1;
+    }
+    if mantissa < 1 << man_bits {
+        biased_exp = 0;
+    }
+    if biased_exp >= exp_mask as i32 {
+        return sign | exp_mask << man_bits;
+    }
+
+    sign | (biased_exp as u16) << man_bits | (mantissa as u16 & ((1 << man_bits) - 1))
+}
+
+#[inline]
+fn decode(bits: u16, exp_bits: u32, man_bits: u32) -> f64 {
+    let sign = if bits >> 15 == 1 { -1.0 } else { 1.0 };
+    let exp_mask = (1 << exp_bits) - 1;
+    let bias = (1 << (exp_bits - 1)) - 1;
+    let biased_exp = (bits >> man_bits) as i32 & exp_mask;
+    let mantissa = (bits & ((1 << man_bits) - 1)) as f64;
+
+    if biased_exp == exp_mask {
+        return if mantissa == 0.0 { sign * f64::INFINITY } else { f64::NAN };
+    }
+    if biased_exp == 0 {
+        return sign * mantissa * 2f64.powi(1 - bias - man_bits as i32);
+    }
+
+    sign * (mantissa + 2f64.powi(man_bits as i32)) * 2f64.powi(biased_exp - bias - man_bits as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bf16_test() {
+        assert_eq!(Bf16::one().to_f64(), 1.0);
+        assert_eq!(Bf16::MAX.to_f64(), 3.3895313892515355E38);
+        assert_eq!(Bf16::EPSILON.to_f64(), 2f64.powi(-7));
+        assert_eq!(Bf16::from_f64(1.0 + 2f64.powi(-8)).to_bits(), 0x3F80); // tie rounds to even
+        assert_eq!(Bf16::from_f64(1.0 + 3.0 * 2f64.powi(-8)).to_bits(), 0x3F82);
+        assert_eq!(Bf16::from_f64(3.14159).to_f64(), 3.140625);
+        assert_eq!(Bf16::from_f64(-2f64.powi(-130)).to_f64(), -2f64.powi(-130)); // subnormal
+        assert_eq!(Bf16::from_f64(1E39).to_f64(), f64::INFINITY);
+        assert!(!Bf16::from_f64(f64::NAN).is_finite());
+        assert_eq!(f32::from_bits((Bf16::from_f64(0.1).to_bits() as u32) << 16) as f64, Bf16::from_f64(0.1).to_f64());
+    }
+
+    #[test]
+    fn f16_test() {
+        assert_eq!(F16::one().to_f64(), 1.0);
+        assert_eq!(F16::MAX.to_f64(), 65504.0);
+        assert_eq!(F16::MIN.to_f64(), -65504.0);
+        assert_eq!(F16::EPSILON.to_f64(), 2f64.powi(-10));
+        assert_eq!(F16::from_f64(0.1).to_bits(), 0x2E66);
+        assert_eq!(F16::from_f64(65520.0).to_f64(), f64::INFINITY);
+        assert_eq!(F16::from_f64(2f64.powi(-24)).to_bits(), 0x0001);
+        assert_eq!(F16::from_f64(2f64.powi(-26)).to_bits(), 0x0000);
+        assert_eq!(F16::from_f64(2f64.powi(-14) - 2f64.powi(-25)).to_bits(), 0x0400); // rounds up to the smallest normal
+
+        let x = F16::from_f64(1.5);
+        let y = F16::from_f64(-0.25);
+        assert_eq!((x * y + x / y).to_f64(), -6.375);
+        assert!(x > y && y < F16::zero());
+        assert_eq!(format!("{:?}", x), "1.5");
+    }
+}
@@ -1,13 +1,52 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
+use std::fmt::Write;
 
-use super::real::Real;
-use super::node::Node;
+use serde::Serialize;
+
+use crate::rng::i64_to_real;
+
+use super::real::{operations::TensorOperations, Real};
+use super::node::{Indices, Node, NodeKind, Operand};
 use super::var::Var;
 
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, PartialEq, PartialOrd, Serialize)]
 pub struct Tape<T: Real> {
     pub(super) nodes: RefCell<Vec<Node<T>>>,
+    pub(super) values: RefCell<Vec<T>>,
     pub(super) num_inputs: RefCell<usize>,
+    #[serde(skip)]
+    anomaly_mode: Cell<bool>,
+    /// The position of the first node with a non-finite value or partial, and whether it was a partial.
+    #[serde(skip)]
+    anomaly: Cell<Option<(usize, bool)>>,
+}
+
+/// The first non-finite value or partial recorded on a `Tape` in anomaly mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TapeAnomaly {
+    pub(super) index: usize,
+    pub(super) name: &'static str,
+    pub(super) partial: bool,
+}
+
+impl TapeAnomaly {
+    /// Returns the index of the first value written by the offending node.
+    #[inline]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the name of the operation that recorded the offending node.
+    #[inline]
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns `true` if the node's values were finite but one of its partials was not.
+    #[inline]
+    pub fn partial(&self) -> bool {
+        self.partial
+    }
 }
 
 impl<T: Real> Default for Tape<T> {
@@ -15,7 +54,10 @@ impl<T: Real> Default for Tape<T> {
     fn default() -> Self {
         Tape {
             nodes: vec![].into(),
+            values: vec![].into(),
             num_inputs: 0.into(),
+            anomaly_mode: false.into(),
+            anomaly: None.into(),
         }
     }
 }
@@ -26,22 +68,61 @@ impl<T: Real> Tape<T> {
         Self::default()
     }
 
+    /// Creates a `Tape` in anomaly mode.
+    #[inline]
+    pub fn with_anomaly_mode() -> Self {
+        let tape = Self::default();
+        tape.set_anomaly_mode(true);
+        tape
+    }
+
+    /// Turns anomaly mode on or off. In anomaly mode, every node is checked for non-finite values and partials 
+    /// as it is recorded, and the first one found is reported by `anomaly`.
+    #[inline]
+    pub fn set_anomaly_mode(&self, on: bool) {
+        self.anomaly_mode.set(on);
+    }
+
+    #[inline]
+    pub fn anomaly_mode(&self) -> bool {
+        self.anomaly_mode.get()
+    }
+
+    /// Returns the first node recorded in anomaly mode that has a non-finite value or partial.
+    #[inline]
+    pub fn anomaly(&self) -> Option<TapeAnomaly> {
+        self.anomaly.get().map(|(n, partial)| {
+            let node = &self.nodes.borrow()[n];
+            TapeAnomaly { index: node.index, name: node.name, partial }
+        })
+    }
+
+    /// Returns the number of values recorded on `self`.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.values.borrow().len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.values.borrow().is_empty()
+    }
+
+    /// Returns the number of operations recorded on `self`.
+    #[inline]
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.borrow().len()
+    }
+
     #[inline]
     pub fn new_var(&self, value: T) -> Var<'_, T> {
-        let len = self.nodes.borrow().len();
-        self.nodes.borrow_mut().push(
-            Node {
-                partials: [T::zero(), T::zero()],
-                // for a single (input) variable, we point the parents to itself
-                parents: [len, len],
-            }
-        );
+        let index = self.push(NodeKind::Input, &[value]);
 
         *self.num_inputs.borrow_mut() += 1;
-        
+
         Var {
             tape: self,
-            index: len,
+            index,
             val: value,
         }
     }
@@ -56,26 +137,425 @@ impl<T: Real> Tape<T> {
         vec
     }
 
+    /// Records a constant, which is not an input and is treated as fixed by `backprop`.
+    #[inline]
+    pub fn constant(&self, value: T) -> Var<'_, T> {
+        Var {
+            tape: self,
+            index: self.push(NodeKind::Constant, &[value]),
+            val: value,
+        }
+    }
+
     #[inline]
     pub fn unary_op(&self, partial: T, index: usize, new_value: T) -> Var<'_, T> {
-        self.binary_op(partial, T::zero(), index, index, new_value)
+        self.named_unary_op("scalar", partial, index, new_value)
     }
 
     #[inline]
     pub fn binary_op(&self, lhs_partial: T, rhs_partial: T, lhs_index: usize, rhs_index: usize, new_value: T) -> Var<'_, T> {
-        let len = self.nodes.borrow().len();
-        self.nodes.borrow_mut().push(
-            Node {
-                partials: [lhs_partial, rhs_partial],
-                // for a single (input) variable, we point the parents to itself
-                parents: [lhs_index, rhs_index],
-            }
-        );
+        self.named_binary_op("scalar", lhs_partial, rhs_partial, lhs_index, rhs_index, new_value)
+    }
+
+    #[inline]
+    pub fn nary_op<'t>(&'t self, partials: &[T], vars: &[Var<'t, T>], new_value: T) -> Var<'t, T> {
+        self.named_nary_op("nary", partials, vars, new_value)
+    }
+
+    /// Same as `unary_op`, but records the node as `name`.
+    #[inline]
+    pub(super) fn named_unary_op(&self, name: &'static str, partial: T, index: usize, new_value: T) -> Var<'_, T> {
+        self.named_binary_op(name, partial, T::zero(), index, index, new_value)
+    }
+
+    /// Same as `binary_op`, but records the node as `name`.
+    #[inline]
+    pub(super) fn named_binary_op(&self, name: &'static str, lhs_partial: T, rhs_partial: T, lhs_index: usize, rhs_index: usize, new_value: T) -> 
+    Var<'_, T> {
+        let kind = NodeKind::Scalar {
+            partials: [lhs_partial, rhs_partial],
+            parents: [lhs_index, rhs_index],
+        };
+
+        Var {
+            tape: self,
+            index: self.push_named(kind, name, &[new_value]),
+            val: new_value,
+        }
+    }
+
+    /// Same as `nary_op`, but records the node as `name`.
+    #[inline]
+    pub(super) fn named_nary_op<'t>(&'t self, name: &'static str, partials: &[T], vars: &[Var<'t, T>], new_value: T) -> Var<'t, T> {
+        assert_eq!(partials.len(), vars.len(), "There must be one partial per var");
+
+        let kind = NodeKind::Nary { parents: Self::indices(vars), partials: partials.to_vec() };
 
         Var {
             tape: self,
-            index: len,
+            index: self.push_named(kind, name, &[new_value]),
             val: new_value,
         }
     }
-}
\ No newline at end of file
+
+    /// Multiplies the row-major `rows`×`inner` matrix `lhs` by the `inner`×`cols` matrix `rhs`,
+    /// recording the whole product as a single node.
+    #[inline]
+    pub fn matmul<'t>(&'t self, lhs: &[Var<'t, T>], rhs: &[Var<'t, T>], rows: usize, cols: usize) -> Vec<Var<'t, T>> {
+        let output = T::matmul(&Self::vals(lhs), &Self::vals(rhs), rows, cols);
+
+        let kind = NodeKind::MatMul {
+            lhs: Self::indices(lhs),
+            rhs: Operand::Var(Self::indices(rhs)),
+            rows,
+            inner: lhs.len() / rows,
+            cols,
+        };
+
+        self.push_vars(kind, output)
+    }
+
+    /// Same as `matmul`, but with a constant `rhs` that gradients do not flow to.
+    #[inline]
+    pub fn matmul_real<'t>(&'t self, lhs: &[Var<'t, T>], rhs: &[T], rows: usize, cols: usize) -> Vec<Var<'t, T>> {
+        let output = T::matmul(&Self::vals(lhs), rhs, rows, cols);
+
+        let kind = NodeKind::MatMul {
+            lhs: Self::indices(lhs),
+            rhs: Operand::Const(rhs.to_vec()),
+            rows,
+            inner: lhs.len() / rows,
+            cols,
+        };
+
+        self.push_vars(kind, output)
+    }
+
+    /// Adds `rhs` to `lhs` elementwise, repeating `rhs` if it is shorter than `lhs`.
+    #[inline]
+    pub fn broadcast_add<'t>(&'t self, lhs: &[Var<'t, T>], rhs: &[Var<'t, T>]) -> Vec<Var<'t, T>> {
+        let output = T::broadcast_add(&Self::vals(lhs), &Self::vals(rhs));
+        let kind = NodeKind::BroadcastAdd {
+            lhs: Self::indices(lhs),
+            rhs: Self::indices(rhs),
+        };
+
+        self.push_vars(kind, output)
+    }
+
+    /// Applies `f` to every element of `values`, where `df` is the derivative of `f`.
+    #[inline]
+    pub fn map<'t>(&'t self, values: &[Var<'t, T>], f: impl Fn(T) -> T, df: impl Fn(T) -> T) -> Vec<Var<'t, T>> {
+        let output = values.iter().map(|x| f(x.val)).collect();
+        let kind = NodeKind::Map {
+            input: Self::indices(values),
+            partials: values.iter().map(|x| df(x.val)).collect(),
+        };
+
+        self.push_vars(kind, output)
+    }
+
+    /// Sums all of `values` into a single node.
+    #[inline]
+    pub fn sum<'t>(&'t self, values: &[Var<'t, T>]) -> Var<'t, T> {
+        let sum = T::sum(&Self::vals(values));
+        let kind = NodeKind::Sum { input: Self::indices(values) };
+
+        self.push_vars(kind, vec![sum])[0]
+    }
+
+    /// Computes the dot product of `values` and the constant `coefficients` as a single node.
+    #[inline]
+    pub fn dot<'t>(&'t self, values: &[Var<'t, T>], coefficients: &[T]) -> Var<'t, T> {
+        self.named_nary_op("dot", coefficients, values, T::dot(&Self::vals(values), coefficients))
+    }
+
+    /// Averages all of `values` into a single node.
+    #[inline]
+    pub fn mean<'t>(&'t self, values: &[Var<'t, T>]) -> Var<'t, T> {
+        let mean = T::mean(&Self::vals(values));
+        let len_recip = i64_to_real::<T>(values.len() as i64).recip();
+        let kind = NodeKind::Mean { input: Self::indices(values), len_recip };
+
+        self.push_vars(kind, vec![mean])[0]
+    }
+
+    /// Renders `self` as a Graphviz DOT graph. Each node is labeled with its position on the tape, its name and its values, 
+    /// and each edge from a parent is labeled with the partial with respect to that parent where one is stored.
+    #[inline]
+    pub fn to_dot(&self) -> String {
+        let nodes = self.nodes.borrow();
+        let values = self.values.borrow();
+
+        // the node that wrote each slot
+        let mut owners = Vec::with_capacity(values.len());
+        for n in 0..nodes.len() {
+            owners.extend(std::iter::repeat_n(n, self.node_len(&nodes, n)));
+        }
+
+        let mut dot = String::from("digraph tape {\n");
+        for (n, node) in nodes.iter().enumerate() {
+            let node_values = &values[node.index..node.index + self.node_len(&nodes, n)];
+            let value_label = if node_values.len() == 1 { format!("{:?}", node_values[0]) } else { format!("{:?}", node_values) };
+            writeln!(dot, "    {} [label=\"#{} {}\\nvalue = {}\"];", n, node.index, node.name, value_label).unwrap();
+
+            let mut unlabeled_parents = Vec::default();
+            for (parent, partial) in node.kind.parents() {
+                match partial {
+                    Some(partial) => writeln!(dot, "    {} -> {} [label=\"{:?}\"];", owners[parent], n, partial).unwrap(),
+                    None if !unlabeled_parents.contains(&owners[parent]) => unlabeled_parents.push(owners[parent]),
+                    None => (),
+                }
+            }
+            for parent in unlabeled_parents {
+                writeln!(dot, "    {} -> {};", parent, n).unwrap();
+            }
+        }
+        dot.push_str("}\n");
+
+        dot
+    }
+
+    /// Serializes every node of `self`, along with the values it produced, as JSON.
+    #[inline]
+    pub fn to_json(&self) -> String 
+    where T: Serialize, {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// Renames the node that wrote the slot at `index`. Operations on `Var` are named as they are recorded, so this is only for user names.
+    #[inline]
+    pub(super) fn name_node(&self, index: usize, name: &'static str) {
+        let mut nodes = self.nodes.borrow_mut();
+        let n = nodes.partition_point(|node| node.index <= index) - 1;
+        nodes[n].name = name;
+    }
+
+    /// Returns the number of slots written by the `n`th node.
+    #[inline]
+    fn node_len(&self, nodes: &[Node<T>], n: usize) -> usize {
+        let end = match nodes.get(n + 1) {
+            Some(next) => next.index,
+            None => self.len(),
+        };
+
+        end - nodes[n].index
+    }
+
+    /// Records `kind` as writing `values`, returning the index of the first value.
+    #[inline]
+    fn push(&self, kind: NodeKind<T>, values: &[T]) -> usize {
+        let name = kind.default_name();
+        self.push_named(kind, name, values)
+    }
+
+    /// Same as `push`, but records the node as `name`.
+    #[inline]
+    fn push_named(&self, kind: NodeKind<T>, name: &'static str, values: &[T]) -> usize {
+        let index = self.values.borrow().len();
+        if self.anomaly_mode.get() && self.anomaly.get().is_none() {
+            let n = self.nodes.borrow().len();
+            if values.iter().any(|val| !val.is_finite()) {
+                self.anomaly.set(Some((n, false)));
+            }
+            else if kind.parents().iter().any(|(_, partial)| partial.is_some_and(|partial| !partial.is_finite())) {
+                self.anomaly.set(Some((n, true)));
+            }
+        }
+
+        self.values.borrow_mut().extend_from_slice(values);
+        self.nodes.borrow_mut().push(Node { index, name, kind });
+
+        index
+    }
+
+    #[inline]
+    fn push_vars(&self, kind: NodeKind<T>, values: Vec<T>) -> Vec<Var<'_, T>> {
+        let index = self.push(kind, &values);
+
+        values
+            .into_iter()
+            .enumerate()
+            .map(|(i, val)| Var { tape: self, index: index + i, val })
+            .collect()
+    }
+
+    #[inline]
+    fn indices(vars: &[Var<'_, T>]) -> Indices {
+        Indices::new(vars.iter().map(|x| x.index))
+    }
+
+    #[inline]
+    fn vals(vars: &[Var<'_, T>]) -> Vec<T> {
+        vars.iter().map(|x| x.val).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autodiff::real::operations::UnaryOperations;
+
+    #[test]
+    fn matmul_test() {
+        let tape = Tape::new();
+        let a = tape.new_vars(&vec![1.0, -2.0, 0.5, 3.0, 4.0, -1.5]);
+        let b = tape.new_vars(&vec![2.0, -1.0, 0.25, 1.5, 3.0, 0.0]);
+
+        // (2x3)(3x2), summed with different weights per element
+        let product = tape.matmul(&a, &b, 2, 2);
+        let z = product[0] + product[1] * 2.0 + product[2] * 3.0 + product[3] * 4.0;
+        let grad = z.backprop();
+
+        let scalar_tape = Tape::new();
+        let a = scalar_tape.new_vars(&vec![1.0, -2.0, 0.5, 3.0, 4.0, -1.5]);
+        let b = scalar_tape.new_vars(&vec![2.0, -1.0, 0.25, 1.5, 3.0, 0.0]);
+        let p00 = a[0] * b[0] + a[1] * b[2] + a[2] * b[4];
+        let p01 = a[0] * b[1] + a[1] * b[3] + a[2] * b[5];
+        let p10 = a[3] * b[0] + a[4] * b[2] + a[5] * b[4];
+        let p11 = a[3] * b[1] + a[4] * b[3] + a[5] * b[5];
+        let scalar_z = p00 + p01 * 2.0 + p10 * 3.0 + p11 * 4.0;
+
+        assert_eq!(z.val(), scalar_z.val());
+        assert_eq!(grad.wrt_inputs(), scalar_z.backprop().wrt_inputs());
+        assert_eq!(tape.nodes.borrow().len(), 12 + 1 + 6);
+    }
+
+    #[test]
+    fn matmul_real_test() {
+        let tape = Tape::new();
+        let w = tape.new_vars(&vec![0.5, -1.0, 2.0, 1.5]);
+        let b = tape.new_vars(&vec![0.1, -0.2]);
+
+        let sums = tape.broadcast_add(&tape.matmul_real(&w, &[3.0, 4.0], 2, 1), &b);
+        let z = tape.sum(&sums);
+        let grad = z.backprop();
+
+        assert_eq!(sums.iter().map(|x| x.val()).collect::<Vec<f64>>(), [-2.4, 11.8]);
+        assert_eq!(grad.wrt_inputs(), [3.0, 4.0, 3.0, 4.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn map_and_mean_test() {
+        let tape = Tape::new();
+        let x = tape.new_vars(&vec![1.0, 2.0, 3.0, 4.0]);
+
+        // mean(x^2) + broadcasted shift
+        let squares = tape.map(&x, |x| x * x, |x| 2.0 * x);
+        let shifted = tape.broadcast_add(&squares, &x[0..2]);
+        let z = tape.mean(&shifted);
+        let grad = z.backprop();
+
+        assert_eq!(z.val(), (1.0 + 4.0 + 9.0 + 16.0 + 1.0 + 2.0 + 1.0 + 2.0) / 4.0);
+        assert_eq!(grad.wrt_inputs(), [1.0, 1.5, 1.5, 2.0]);
+    }
+
+    #[test]
+    fn sum_and_dot_test() {
+        let tape = Tape::new();
+        let x = tape.new_vars(&vec![1.5, -2.0, 0.5]);
+
+        // sum(x) * dot(x, [2, 0, -4])
+        let z = tape.sum(&x) * tape.dot(&x, &[2.0, 0.0, -4.0]);
+        let grad = z.backprop();
+
+        let scalar_tape = Tape::new();
+        let y = scalar_tape.new_vars(&vec![1.5, -2.0, 0.5]);
+        let scalar_z = (y[0] + y[1] + y[2]) * (y[0] * 2.0 + y[1] * 0.0 + y[2] * -4.0);
+
+        assert_eq!(z.val(), scalar_z.val());
+        assert_eq!(grad.wrt_inputs(), scalar_z.backprop().wrt_inputs());
+        assert_eq!(tape.len(), 6);
+        assert!(scalar_tape.len() > 10);
+    }
+
+    #[test]
+    fn to_dot_test() {
+        let tape = Tape::new();
+        let x = tape.new_var(2.0);
+        let y = tape.new_var(-1.0);
+        let z = (x * y).sin() + tape.sum(&[x, y]);
+
+        assert_eq!(z.val(), (-2f64).sin() + 1.0);
+        assert_eq!(tape.to_dot(), format!("digraph tape {{
+    0 [label=\"#0 input\\nvalue = 2.0\"];
+    1 [label=\"#1 input\\nvalue = -1.0\"];
+    2 [label=\"#2 mul\\nvalue = -2.0\"];
+    0 -> 2 [label=\"-1.0\"];
+    1 -> 2 [label=\"2.0\"];
+    3 [label=\"#3 sin\\nvalue = {:?}\"];
+    2 -> 3 [label=\"{:?}\"];
+    4 [label=\"#4 sum\\nvalue = 1.0\"];
+    0 -> 4 [label=\"1.0\"];
+    1 -> 4 [label=\"1.0\"];
+    5 [label=\"#5 add\\nvalue = {:?}\"];
+    3 -> 5 [label=\"1.0\"];
+    4 -> 5 [label=\"1.0\"];
+}}
+", (-2f64).sin(), (-2f64).cos(), z.val()));
+
+        // operations built from others are recorded under their own names
+        let tape = Tape::new();
+        let x = tape.new_var(2.0);
+        let _ = -(x.ln() + x.log10());
+        let names: Vec<&str> = tape.nodes.borrow().iter().map(|node| node.name).collect();
+        assert_eq!(names, ["input", "ln", "log10", "add", "neg"]);
+    }
+
+    #[test]
+    fn to_dot_tensor_test() {
+        let tape = Tape::new();
+        let w = tape.new_vars(&vec![1.0, 2.0]);
+        let product = tape.matmul_real(&w, &[0.5], 2, 1);
+        let activations = tape.map(&product, |x| x * x, |x| 2.0 * x);
+        activations[1].named("square");
+
+        let dot = tape.to_dot();
+
+        assert!(dot.contains("    2 [label=\"#2 matmul\\nvalue = [0.5, 1.0]\"];\n    0 -> 2;\n    1 -> 2;\n"));
+        assert!(dot.contains("    3 [label=\"#4 square\\nvalue = [0.25, 1.0]\"];\n    2 -> 3 [label=\"1.0\"];\n    2 -> 3 [label=\"2.0\"];\n"));
+    }
+
+    #[test]
+    fn to_json_test() {
+        let tape = Tape::new();
+        let x = tape.new_var(2.0);
+        let _ = x.exp().named("e^x");
+
+        assert_eq!(tape.to_json(), format!(
+            "{{\"nodes\":[{{\"index\":0,\"name\":\"input\",\"kind\":\"Input\"}},\
+            {{\"index\":1,\"name\":\"e^x\",\"kind\":{{\"Scalar\":{{\"partials\":[{0:?},0.0],\"parents\":[0,0]}}}}}}],\
+            \"values\":[2.0,{0:?}],\"num_inputs\":1}}", 2f64.exp()));
+    }
+
+    #[test]
+    fn anomaly_test() {
+        let tape = Tape::with_anomaly_mode();
+        let x = tape.new_var(0f64);
+        let y = tape.new_var(-1.0);
+        let z = (x.sqrt() + y.ln()) * x;
+
+        assert!(z.val().is_nan());
+        assert_eq!(tape.anomaly(), Some(TapeAnomaly { index: 2, name: "sqrt", partial: true }));
+
+        let tape = Tape::new();
+        let x = tape.new_var(-1.0);
+        let _ = x.ln();
+
+        assert_eq!(tape.anomaly(), None);
+
+        tape.set_anomaly_mode(true);
+        let _ = x.ln().named("log");
+
+        assert_eq!(tape.anomaly(), Some(TapeAnomaly { index: 2, name: "log", partial: false }));
+    }
+
+    #[test]
+    fn indices_test() {
+        let tape = Tape::new();
+        let x = tape.new_vars(&vec![1.0, 2.0, 3.0]);
+
+        assert_eq!(Tape::indices(&x), Indices::Range(0, 3));
+        assert_eq!(Tape::indices(&[x[2], x[0]]), Indices::List(vec![2, 0]));
+    }
+}
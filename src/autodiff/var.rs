@@ -1,7 +1,9 @@
 use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
 
 use super::grad::Grad;
-use super::real::operations::{BinaryOperations, OperateWithReal, UnaryOperations};
+use crate::rng::i64_to_real;
+
+use super::real::operations::{BinaryOperations, Clamp, OperateWithReal, TensorOperations, UnaryOperations};
 use super::real::Real;
 use super::tape::Tape;
 
@@ -17,25 +19,15 @@ impl<'t, T: Real> Var<'t, T> {
     #[inline]
     pub fn backprop(&self) -> Grad<T> {
         // vector storing the gradients
-        let tape_len = self.tape.nodes.borrow().len();
+        let tape_len = self.tape.len();
         let mut grad = vec![T::zero(); tape_len];
         grad[self.index] = T::one();
 
-        for i in (0..tape_len).rev() {
-            let node = self.tape.nodes.borrow()[i];
-            // increment gradient contribution to the left parent
-            let lhs_dep = node.parents[0];
-            let lhs_partial = node.partials[0];
-            let grad_i = grad[i];
-            grad[lhs_dep] = grad[lhs_dep] + lhs_partial * grad_i;
-
-            // increment gradient contribution to the right parent
-            // note that in cases of unary operations, because
-            // partial was set to zero, it won't affect the computation
-            let rhs_dep = node.parents[1];
-            let rhs_partial = node.partials[1];
-            let grad_i = grad[i];
-            grad[rhs_dep] = grad[rhs_dep] + rhs_partial * grad_i;
+        // nodes recorded after `self` cannot contribute to its gradient
+        let nodes = self.tape.nodes.borrow();
+        let values = self.tape.values.borrow();
+        for node in nodes.iter().rev().skip_while(|node| node.index > self.index) {
+            node.backward(&values, &mut grad);
         }
 
         Grad { partials: grad, num_inputs: *self.tape.num_inputs.borrow() }
@@ -52,6 +44,59 @@ impl<'t, T: Real> Var<'t, T> {
     pub fn val(self) -> T {
         self.val
     }
+
+    /// Returns a constant with the value of `self`, with no connection to the operations that produced `self`.
+    #[inline]
+    pub fn detach(self) -> Self {
+        self.tape.constant(self.val)
+    }
+
+    /// Returns `self` unchanged, but stops any gradient from flowing back through it.
+    /// Unlike `detach`, the result stays connected to `self` on the tape.
+    #[inline]
+    pub fn stop_gradient(self) -> Self {
+        self.tape.named_unary_op("stop_gradient", T::zero(), self.index, self.val)
+    }
+
+    /// Names the operation that produced `self`, which is shown by `Tape::to_dot`.
+    #[inline]
+    pub fn named(self, name: &'static str) -> Self {
+        self.tape.name_node(self.index, name);
+        self
+    }
+
+    /// Records a user-defined operation of `self`, given its value and its derivative at `self`.
+    #[inline]
+    pub fn custom_unary(self, value: T, partial: T) -> Self {
+        self.tape.unary_op(partial, self.index, value)
+    }
+
+    /// Records a user-defined operation of `self` and `rhs`, given its value and its partials with respect to each.
+    #[inline]
+    pub fn custom_binary(self, rhs: Self, value: T, partials: [T; 2]) -> Self {
+        self.tape.binary_op(partials[0], partials[1], self.index, rhs.index, value)
+    }
+
+    /// Records a user-defined operation of all of `vars`, given its value and its partial with respect to each var.
+    #[inline]
+    pub fn custom_nary(vars: &[Self], value: T, partials: &[T]) -> Self {
+        Self::slice_tape(vars).nary_op(partials, vars, value)
+    }
+
+    /// Returns the tape shared by all of `vars`.
+    #[inline]
+    fn slice_tape(vars: &[Self]) -> &'t Tape<T> {
+        match vars.first() {
+            Some(var) => var.tape,
+            None => panic!("Cannot record an operation on no vars"),
+        }
+    }
+
+    /// Records the logarithm of `self` to a constant `base` as `name`.
+    #[inline]
+    fn log_named(self, name: &'static str, base: T) -> Self {
+        self.tape.named_unary_op(name, (self.val * base.ln()).recip(), self.index, self.val.log(base))
+    }
 }
 
 // negation
@@ -60,7 +105,7 @@ impl<'a, T: Real> Neg for Var<'a, T> {
 
     #[inline]
     fn neg(self) -> Self::Output {
-        self * -T::one()
+        self.tape.named_unary_op("neg", -T::one(), self.index, -self.val)
     }
 }
 
@@ -70,7 +115,7 @@ impl<'t, T: Real> Add for Var<'t, T> {
 
     #[inline]
     fn add(self, rhs: Var<'t, T>) -> Self::Output {
-        self.tape.binary_op(T::one(), T::one(), self.index, rhs.index, self.val + rhs.val)
+        self.tape.named_binary_op("add", T::one(), T::one(), self.index, rhs.index, self.val + rhs.val)
     }
 }
 
@@ -79,7 +124,7 @@ impl<'t, T: Real> Add<T> for Var<'t, T> {
 
     #[inline]
     fn add(self, rhs: T) -> Self::Output {
-        self.tape.unary_op(T::one(), self.index, self.val + rhs)
+        self.tape.named_unary_op("add", T::one(), self.index, self.val + rhs)
     }
 }
 
@@ -89,7 +134,7 @@ impl<'t, T: Real> Sub for Var<'t, T> {
 
     #[inline]
     fn sub(self, rhs: Var<'t, T>) -> Self::Output {
-        self.tape.binary_op(T::one(), -T::one(), self.index, rhs.index, self.val - rhs.val)
+        self.tape.named_binary_op("sub", T::one(), -T::one(), self.index, rhs.index, self.val - rhs.val)
     }
 }
 
@@ -98,7 +143,7 @@ impl<'t, T: Real> Sub<T> for Var<'t, T> {
 
     #[inline]
     fn sub(self, rhs: T) -> Self::Output {
-        self.tape.unary_op(T::one(), self.index, self.val - rhs)
+        self.tape.named_unary_op("sub", T::one(), self.index, self.val - rhs)
     }
 }
 
@@ -108,7 +153,7 @@ impl<'t, T: Real> Mul for Var<'t, T> {
 
     #[inline]
     fn mul(self, rhs: Self) -> Self::Output {
-        self.tape.binary_op(rhs.val, self.val, self.index, rhs.index, self.val * rhs.val)
+        self.tape.named_binary_op("mul", rhs.val, self.val, self.index, rhs.index, self.val * rhs.val)
     }
 }
 
@@ -117,7 +162,7 @@ impl<'t, T: Real> Mul<T> for Var<'t, T> {
 
     #[inline]
     fn mul(self, rhs: T) -> Self::Output {
-        self.tape.unary_op(rhs, self.index, self.val * rhs)
+        self.tape.named_unary_op("mul", rhs, self.index, self.val * rhs)
     }
 }
 
@@ -127,7 +172,7 @@ impl<'t, T: Real> Div for Var<'t, T> {
 
     #[inline]
     fn div(self, rhs: Self) -> Self::Output {
-        self.tape.binary_op(rhs.val.recip(), self.val * -T::one() / (rhs.val * rhs.val), self.index, rhs.index, self.val / rhs.val)
+        self.tape.named_binary_op("div", rhs.val.recip(), self.val * -T::one() / (rhs.val * rhs.val), self.index, rhs.index, self.val / rhs.val)
     }
 }
 
@@ -136,7 +181,7 @@ impl<'t, T: Real> Div<T> for Var<'t, T> {
 
     #[inline]
     fn div(self, rhs: T) -> Self::Output {
-        self.tape.unary_op(rhs.recip(), self.index, self.val / rhs)
+        self.tape.named_unary_op("div", rhs.recip(), self.index, self.val / rhs)
     }
 }
 
@@ -146,7 +191,7 @@ impl<'t, T: Real> Rem for Var<'t, T> {
 
     #[inline]
     fn rem(self, rhs: Self) -> Self::Output {
-        self.tape.binary_op(T::one(), (-self.val / rhs.val).trunc(), self.index, rhs.index, self.val % rhs.val)
+        self.tape.named_binary_op("rem", T::one(), (-self.val / rhs.val).trunc(), self.index, rhs.index, self.val % rhs.val)
     }
 }
 
@@ -155,54 +200,54 @@ impl<'t, T: Real> Rem<T> for Var<'t, T> {
 
     #[inline]
     fn rem(self, rhs: T) -> Self::Output {
-        self.tape.unary_op(T::one(), self.index, self.val % rhs)
+        self.tape.named_unary_op("rem", T::one(), self.index, self.val % rhs)
     }
 }
 
 impl<'t, T: Real> UnaryOperations for Var<'t, T> {
     #[inline]
     fn recip(self) -> Self {
-        self.tape.unary_op(-T::one() / (self.val * self.val), self.index, self.val.recip())
+        self.tape.named_unary_op("recip", -T::one() / (self.val * self.val), self.index, self.val.recip())
     }
 
     #[inline]
     fn abs(self) -> Self {
         // technically the partial should be if zero { NAN } else { signum }, but this shouldn't make a difference
-        self.tape.unary_op(self.val.signum(), self.index, self.val.abs())
+        self.tape.named_unary_op("abs", self.val.signum(), self.index, self.val.abs())
     }
 
     #[inline]
     fn signum(self) -> Self {
         // technically the partial should be if zero { NAN } else { 0.0 }, but this shouldn't make a difference
-        self.tape.unary_op(T::zero(), self.index, self.val.signum())
+        self.tape.named_unary_op("signum", T::zero(), self.index, self.val.signum())
     }
 
     #[inline]
     fn sqrt(self) -> Self {
         let two = T::one() + T::one();
-        self.tape.unary_op(self.val.powf(-two.recip()) / two, self.index, self.val.sqrt())
+        self.tape.named_unary_op("sqrt", self.val.powf(-two.recip()) / two, self.index, self.val.sqrt())
     }
 
     #[inline]
     fn exp(self) -> Self {
-        self.tape.unary_op(self.val.exp(), self.index, self.val.exp())
+        self.tape.named_unary_op("exp", self.val.exp(), self.index, self.val.exp())
     }
 
     #[inline]
     fn exp2(self) -> Self {
-        self.tape.unary_op(T::one().ln_1p() * self.val.exp(), self.index, self.val.exp())
+        self.tape.named_unary_op("exp2", T::one().ln_1p() * self.val.exp(), self.index, self.val.exp())
     }
 
     #[inline]
     fn ln(self) -> Self {
         let e = T::exp(T::one());
-        self.log(e)
+        self.log_named("ln", e)
     }
 
     #[inline]
     fn log2(self) -> Self {
         let two = T::one() + T::one();
-        self.log(two)
+        self.log_named("log2", two)
     }
 
     #[inline]
@@ -210,65 +255,65 @@ impl<'t, T: Real> UnaryOperations for Var<'t, T> {
         let two = T::one() + T::one();
         let eight = two * two * two;
         let ten = eight + two;
-        self.log(ten)
+        self.log_named("log10", ten)
     }
 
     #[inline]
     fn cbrt(self) -> Self {
         let two = T::one() + T::one();
         let three = two + T::one();
-        self.tape.unary_op(self.val.powf(-two / three) / three, self.index, self.val.cbrt())
+        self.tape.named_unary_op("cbrt", self.val.powf(-two / three) / three, self.index, self.val.cbrt())
     }
 
     #[inline]
     fn sin(self) -> Self {
-        self.tape.unary_op(self.val.cos(), self.index, self.val.sin())
+        self.tape.named_unary_op("sin", self.val.cos(), self.index, self.val.sin())
     }
 
     #[inline]
     fn cos(self) -> Self {
-        self.tape.unary_op(-self.val.sin(), self.index, self.val.cos())
+        self.tape.named_unary_op("cos", -self.val.sin(), self.index, self.val.cos())
     }
 
     #[inline]
     fn tan(self) -> Self {
         let cos_val = self.val.cos();
-        self.tape.unary_op(T::one() / (cos_val * cos_val), self.index, self.val.tan())
+        self.tape.named_unary_op("tan", T::one() / (cos_val * cos_val), self.index, self.val.tan())
     }
     
     #[inline]
     fn asin(self) -> Self {
-        self.tape.unary_op((T::one() - self.val.powf(T::one() + T::one())).sqrt().recip(), self.index, self.val.asin())
+        self.tape.named_unary_op("asin", (T::one() - self.val.powf(T::one() + T::one())).sqrt().recip(), self.index, self.val.asin())
     }
     
     #[inline]
     fn acos(self) -> Self {
-        self.tape.unary_op(-(T::one() - self.val.powf(T::one() + T::one())).sqrt().recip(), self.index, self.val.acos())
+        self.tape.named_unary_op("acos", -(T::one() - self.val.powf(T::one() + T::one())).sqrt().recip(), self.index, self.val.acos())
     }
     
     #[inline]
     fn atan(self) -> Self {
-        self.tape.unary_op((self.val.powf(T::one() + T::one()) + T::one()).recip(), self.index, self.val.atan())
+        self.tape.named_unary_op("atan", (self.val.powf(T::one() + T::one()) + T::one()).recip(), self.index, self.val.atan())
     }
     
     #[inline]
     fn exp_m1(self) -> Self {
-        self.tape.unary_op(self.val.exp(), self.index, self.val.exp_m1())
+        self.tape.named_unary_op("exp_m1", self.val.exp(), self.index, self.val.exp_m1())
     }
     
     #[inline]
     fn ln_1p(self) -> Self {
-        self.tape.unary_op((self.val + T::one()).recip(), self.index, self.val.ln_1p())
+        self.tape.named_unary_op("ln_1p", (self.val + T::one()).recip(), self.index, self.val.ln_1p())
     }
     
     #[inline]
     fn sinh(self) -> Self {
-        self.tape.unary_op(self.val.cosh(), self.index, self.val.sinh())
+        self.tape.named_unary_op("sinh", self.val.cosh(), self.index, self.val.sinh())
     }
     
     #[inline]
     fn cosh(self) -> Self {
-        self.tape.unary_op(self.val.sinh(), self.index, self.val.cosh())
+        self.tape.named_unary_op("cosh", self.val.sinh(), self.index, self.val.cosh())
     }
 
     #[inline]
@@ -276,42 +321,54 @@ impl<'t, T: Real> UnaryOperations for Var<'t, T> {
         let two = T::one() + T::one();
         let four = two + two;
         let twox = two * self.val;
-        self.tape.unary_op(four * (twox).exp() / ((twox).exp() + T::one()).powf(two), self.index, self.val.tanh())
+        self.tape.named_unary_op("tanh", four * (twox).exp() / ((twox).exp() + T::one()).powf(two), self.index, self.val.tanh())
     }
     
     #[inline]
     fn asinh(self) -> Self {
-        self.tape.unary_op((self.val.powf(T::one() + T::one()) + T::one()).sqrt().recip(), self.index, self.val.asinh())
+        self.tape.named_unary_op("asinh", (self.val.powf(T::one() + T::one()) + T::one()).sqrt().recip(), self.index, self.val.asinh())
     }
     
     #[inline]
     fn acosh(self) -> Self {
-        self.tape.unary_op(((self.val - T::one()).sqrt() * (self.val + T::one()).sqrt()).recip(), self.index, self.val.acosh())
+        self.tape.named_unary_op("acosh", ((self.val - T::one()).sqrt() * (self.val + T::one()).sqrt()).recip(), self.index, self.val.acosh())
     }
     
     #[inline]
     fn atanh(self) -> Self {
-        self.tape.unary_op((T::one() - self.val.powf(T::one() + T::one())).recip(), self.index, self.val.atanh())
+        self.tape.named_unary_op("atanh", (T::one() - self.val.powf(T::one() + T::one())).recip(), self.index, self.val.atanh())
     }
     
     #[inline]
     fn trunc(self) -> Self {
-        self.tape.unary_op(T::zero(), self.index, self.val.trunc())
+        self.tape.named_unary_op("trunc", T::zero(), self.index, self.val.trunc())
     }
     
     #[inline]
     fn floor(self) -> Self {
-        self.tape.unary_op(T::zero(), self.index, self.val.floor())
+        self.tape.named_unary_op("floor", T::zero(), self.index, self.val.floor())
     }
     
     #[inline]
     fn ceil(self) -> Self {
-        self.tape.unary_op(T::zero(), self.index, self.val.ceil())
+        self.tape.named_unary_op("ceil", T::zero(), self.index, self.val.ceil())
     }
     
     #[inline]
     fn round(self) -> Self {
-        self.tape.unary_op(T::zero(), self.index, self.val.round())
+        self.tape.named_unary_op("round", T::zero(), self.index, self.val.round())
+    }
+
+    #[inline]
+    fn square(self) -> Self {
+        self.tape.named_unary_op("square", (T::one() + T::one()) * self.val, self.index, self.val.square())
+    }
+
+    #[inline]
+    fn powi(self, n: i32) -> Self {
+        // avoids 0 * inf when differentiating x^0 at 0
+        let partial = if n == 0 { T::zero() } else { i64_to_real::<T>(n as i64) * self.val.powi(n - 1) };
+        self.tape.named_unary_op("powi", partial, self.index, self.val.powi(n))
     }
 }
 
@@ -320,7 +377,7 @@ impl<'t, T: Real> BinaryOperations for Var<'t, T> {
     #[inline]
     fn log(self, base: Self) -> Self {
         let base_ln: T = base.val.ln();
-        self.tape.binary_op(
+        self.tape.named_binary_op("log", 
             (self.val * base_ln).recip(),
             -self.val.ln() / (base.val * base_ln * base_ln),
             self.index, base.index, self.val.log(base.val))
@@ -328,7 +385,36 @@ impl<'t, T: Real> BinaryOperations for Var<'t, T> {
 
     #[inline]
     fn powf(self, n: Self) -> Self {
-        self.tape.binary_op(n.val * self.val.powf(n.val - T::one()), self.val.powf(n.val) * self.val.ln(), self.index, n.index, self.val.powf(n.val))
+        self.tape.named_binary_op("powf", n.val * self.val.powf(n.val - T::one()), self.val.powf(n.val) * self.val.ln(), self.index, n.index, self.val.powf(n.val))
+    }
+
+    #[inline]
+    fn min(self, other: Self) -> Self {
+        let partials = min_partials(self.val, other.val);
+        self.tape.named_binary_op("min", partials[0], partials[1], self.index, other.index, self.val.min(other.val))
+    }
+
+    #[inline]
+    fn max(self, other: Self) -> Self {
+        let partials = max_partials(self.val, other.val);
+        self.tape.named_binary_op("max", partials[0], partials[1], self.index, other.index, self.val.max(other.val))
+    }
+
+    #[inline]
+    fn hypot(self, other: Self) -> Self {
+        let partials = hypot_partials(self.val, other.val);
+        self.tape.named_binary_op("hypot", partials[0], partials[1], self.index, other.index, self.val.hypot(other.val))
+    }
+
+    #[inline]
+    fn atan2(self, other: Self) -> Self {
+        let partials = atan2_partials(self.val, other.val);
+        self.tape.named_binary_op("atan2", partials[0], partials[1], self.index, other.index, self.val.atan2(other.val))
+    }
+
+    #[inline]
+    fn mul_add(self, a: Self, b: Self) -> Self {
+        self.tape.named_nary_op("mul_add", &[a.val, self.val, T::one()], &[self, a, b], self.val.mul_add(a.val, b.val))
     }
 }
 
@@ -336,12 +422,37 @@ impl<'t, T: Real> BinaryOperations for Var<'t, T> {
 impl<'t, T: Real> BinaryOperations<T> for Var<'t, T> {
     #[inline]
     fn log(self, base: T) -> Self {
-        self.tape.unary_op((self.val * base.ln()).recip(), self.index, self.val.log(base))
+        self.log_named("log", base)
     }
 
     #[inline]
     fn powf(self, n: T) -> Self {
-        self.tape.unary_op(n * self.val.powf(n - T::one()), self.index, self.val.powf(n))
+        self.tape.named_unary_op("powf", n * self.val.powf(n - T::one()), self.index, self.val.powf(n))
+    }
+
+    #[inline]
+    fn min(self, other: T) -> Self {
+        self.tape.named_unary_op("min", min_partials(self.val, other)[0], self.index, self.val.min(other))
+    }
+
+    #[inline]
+    fn max(self, other: T) -> Self {
+        self.tape.named_unary_op("max", max_partials(self.val, other)[0], self.index, self.val.max(other))
+    }
+
+    #[inline]
+    fn hypot(self, other: T) -> Self {
+        self.tape.named_unary_op("hypot", hypot_partials(self.val, other)[0], self.index, self.val.hypot(other))
+    }
+
+    #[inline]
+    fn atan2(self, other: T) -> Self {
+        self.tape.named_unary_op("atan2", atan2_partials(self.val, other)[0], self.index, self.val.atan2(other))
+    }
+
+    #[inline]
+    fn mul_add(self, a: T, b: T) -> Self {
+        self.tape.named_unary_op("mul_add", a, self.index, self.val.mul_add(a, b))
     }
 }
 
@@ -349,20 +460,142 @@ impl<'t, T: Real> BinaryOperations<T> for Var<'t, T> {
 impl<'t, T: Real> BinaryOperations<Var<'t, T>, Var<'t, T>> for T {
     #[inline]
     fn log(self, base: Var<'t, T>) -> Var<'t, T> {
-        base.tape.unary_op(-self.ln() / (base.val * base.val.ln() * base.val.ln()), base.index, self.log(base.val))
+        base.tape.named_unary_op("log", -self.ln() / (base.val * base.val.ln() * base.val.ln()), base.index, self.log(base.val))
     }
 
     #[inline]
     fn powf(self, n: Var<'t, T>) -> Var<'t, T> {
-        n.tape.unary_op(self.powf(n.val) * self.ln(), n.index, self.powf(n.val))
+        n.tape.named_unary_op("powf", self.powf(n.val) * self.ln(), n.index, self.powf(n.val))
+    }
+
+    #[inline]
+    fn min(self, other: Var<'t, T>) -> Var<'t, T> {
+        other.tape.named_unary_op("min", min_partials(self, other.val)[1], other.index, self.min(other.val))
+    }
+
+    #[inline]
+    fn max(self, other: Var<'t, T>) -> Var<'t, T> {
+        other.tape.named_unary_op("max", max_partials(self, other.val)[1], other.index, self.max(other.val))
+    }
+
+    #[inline]
+    fn hypot(self, other: Var<'t, T>) -> Var<'t, T> {
+        other.tape.named_unary_op("hypot", hypot_partials(self, other.val)[1], other.index, self.hypot(other.val))
+    }
+
+    #[inline]
+    fn atan2(self, other: Var<'t, T>) -> Var<'t, T> {
+        other.tape.named_unary_op("atan2", atan2_partials(self, other.val)[1], other.index, self.atan2(other.val))
+    }
+
+    #[inline]
+    fn mul_add(self, a: Var<'t, T>, b: Var<'t, T>) -> Var<'t, T> {
+        a.tape.named_binary_op("mul_add", self, T::one(), a.index, b.index, self.mul_add(a.val, b.val))
+    }
+}
+
+// var.clamp(var, var) and var.clamp(T, T)
+impl<'t, T: Real> Clamp for Var<'t, T> {
+    #[inline]
+    fn clamp(self, min: Self, max: Self) -> Self {
+        let half = (T::one() + T::one()).recip();
+        let partials = if self.val < min.val { [T::zero(), T::one(), T::zero()] }
+            else if self.val == min.val { [half, half, T::zero()] }
+            else if self.val > max.val { [T::zero(), T::zero(), T::one()] }
+            else if self.val == max.val { [half, T::zero(), half] }
+            else { [T::one(), T::zero(), T::zero()] };
+
+        self.tape.named_nary_op("clamp", &partials, &[self, min, max], self.val.clamp(min.val, max.val))
     }
 }
 
+impl<'t, T: Real> Clamp<T> for Var<'t, T> {
+    #[inline]
+    fn clamp(self, min: T, max: T) -> Self {
+        let half = (T::one() + T::one()).recip();
+        let partial = if self.val < min || self.val > max { T::zero() }
+            else if self.val == min || self.val == max { half }
+            else { T::one() };
+
+        self.tape.named_unary_op("clamp", partial, self.index, self.val.clamp(min, max))
+    }
+}
+
+/// Returns the partials of `lhs.min(rhs)`. At a tie, the gradient is split evenly between both.
+#[inline]
+fn min_partials<T: Real>(lhs: T, rhs: T) -> [T; 2] {
+    let half = (T::one() + T::one()).recip();
+    if lhs < rhs { [T::one(), T::zero()] }
+    else if rhs < lhs { [T::zero(), T::one()] }
+    else { [half, half] }
+}
+
+/// Returns the partials of `lhs.max(rhs)`. At a tie, the gradient is split evenly between both.
+#[inline]
+fn max_partials<T: Real>(lhs: T, rhs: T) -> [T; 2] {
+    let half = (T::one() + T::one()).recip();
+    if lhs > rhs { [T::one(), T::zero()] }
+    else if rhs > lhs { [T::zero(), T::one()] }
+    else { [half, half] }
+}
+
+/// Returns the partials of `lhs.hypot(rhs)`, using zero as the subgradient at the origin.
+#[inline]
+fn hypot_partials<T: Real>(lhs: T, rhs: T) -> [T; 2] {
+    let hypot = lhs.hypot(rhs);
+    if hypot == T::zero() { return [T::zero(), T::zero()] };
+
+    [lhs / hypot, rhs / hypot]
+}
+
+/// Returns the partials of `y.atan2(x)`, using zero as the subgradient at the origin.
+#[inline]
+fn atan2_partials<T: Real>(y: T, x: T) -> [T; 2] {
+    let denom = x * x + y * y;
+    if denom == T::zero() { return [T::zero(), T::zero()] };
+
+    [x / denom, -y / denom]
+}
+
 impl<'t, T: Real> OperateWithReal<T> for Var<'t, T> {
 }
 
+impl<'t, T: Real> TensorOperations<T> for Var<'t, T> {
+    #[inline]
+    fn matmul(lhs: &[Self], rhs: &[Self], rows: usize, cols: usize) -> Vec<Self> {
+        Self::slice_tape(lhs).matmul(lhs, rhs, rows, cols)
+    }
+
+    #[inline]
+    fn matmul_real(lhs: &[Self], rhs: &[T], rows: usize, cols: usize) -> Vec<Self> {
+        Self::slice_tape(lhs).matmul_real(lhs, rhs, rows, cols)
+    }
+
+    #[inline]
+    fn broadcast_add(lhs: &[Self], rhs: &[Self]) -> Vec<Self> {
+        Self::slice_tape(lhs).broadcast_add(lhs, rhs)
+    }
+
+    #[inline]
+    fn sum(values: &[Self]) -> Self {
+        Self::slice_tape(values).sum(values)
+    }
+
+    #[inline]
+    fn dot(values: &[Self], coefficients: &[T]) -> Self {
+        Self::slice_tape(values).dot(values, coefficients)
+    }
+
+    #[inline]
+    fn mean(values: &[Self]) -> Self {
+        Self::slice_tape(values).mean(values)
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::autodiff::real::real_math::RealMath;
+
     use super::*;
 
     #[test]
@@ -482,6 +715,131 @@ mod tests {
         assert_eq!(grad.full().len(), 28);
     }
 
+    #[test]
+    fn custom_op_test() {
+        // an exact erf, with the value supplied from elsewhere
+        fn erf<'t>(x: Var<'t, f64>, value: f64) -> Var<'t, f64> {
+            x.custom_unary(value, 2.0 / std::f64::consts::PI.sqrt() * (-x.val() * x.val()).exp())
+        }
+
+        // linear interpolation between two table entries, where t is the position between them
+        fn lerp<'t>(a: Var<'t, f64>, b: Var<'t, f64>, t: f64) -> Var<'t, f64> {
+            a.custom_binary(b, a.val() + (b.val() - a.val()) * t, [1.0 - t, t])
+        }
+
+        let tape = Tape::new();
+        let x = tape.new_var(0.5);
+        let y = tape.new_var(2.0);
+        let a = tape.new_var(-1.0);
+
+        // z = erf(x) + lerp(y, a, 0.25) * weighted_sum(x, y, a)
+        let weighted_sum = Var::custom_nary(&[x, y, a], x.val() + y.val() * 2.0 + a.val() * 3.0, &[1.0, 2.0, 3.0]);
+        let z = erf(x, 0.5204998778130465) + lerp(y, a, 0.25) * weighted_sum;
+        let grad = z.backprop();
+
+        assert_eq!(tape.len(), 8);
+        assert_eq!(z.val(), 0.5204998778130465 + 1.25 * 1.5);
+        assert_eq!(grad.wrt_inputs().iter().map(|x| (x * 1E5f64).round() / 1E5).collect::<Vec<f64>>(), [2.12878, 3.625, 4.125]);
+    }
+
+    fn min_max_etc<U>(x: U, y: U, z: U) -> U
+    where U: RealMath + OperateWithReal<f64> + Clamp + Clamp<f64>, f64: BinaryOperations<U, U>, {
+        x.min(y) + x.max(z) * y.hypot(z) + y.atan2(x) + x.powi(3) - z.square() + x.mul_add(y, z) + y.clamp(x, z) +
+            x.min(0.3) * y.max(-0.2) + x.hypot(2.0) + z.atan2(1.5) + y.mul_add(2.0, 1.0) + z.clamp(-1.0, 1.0) * z.powi(-2) +
+            0.3.min(z) + 1.2.max(y) + 2.0.hypot(x) * 0.7.atan2(y) + 1.5.mul_add(x, z)
+    }
+
+    /// Central finite difference of `f` with respect to each of `inputs`.
+    fn finite_diff(f: impl Fn(&[f64]) -> f64, inputs: &[f64]) -> Vec<f64> {
+        let h = 1E-6;
+        (0..inputs.len()).map(|i| {
+            let mut plus = inputs.to_vec();
+            let mut minus = inputs.to_vec();
+            plus[i] += h;
+            minus[i] -= h;
+
+            (f(&plus) - f(&minus)) / (2.0 * h)
+        }).collect()
+    }
+
+    #[test]
+    fn min_max_etc_test() {
+        for inputs in [[0.4, -0.7, 1.3], [-1.1, 0.9, 0.2], [-0.6, 1.7, 2.5]] {
+            let tape = Tape::new();
+            let vars = tape.new_vars(&inputs.to_vec());
+            let z = min_max_etc(vars[0], vars[1], vars[2]);
+            let grad = z.backprop();
+
+            let expected = finite_diff(|x| min_max_etc(x[0], x[1], x[2]), &inputs);
+
+            assert_eq!(z.val(), min_max_etc(inputs[0], inputs[1], inputs[2]));
+            for (actual, expected) in grad.wrt_inputs().iter().zip(expected) {
+                assert!((actual - expected).abs() < 1E-6, "{} != {} at {:?}", actual, expected, inputs);
+            }
+        }
+    }
+
+    #[test]
+    fn kinks_test() {
+        let tape = Tape::new();
+        let x = tape.new_var(0.5);
+        let y = tape.new_var(0.5);
+        let zero = tape.new_var(0.0);
+
+        assert_eq!(x.min(y).backprop().wrt_inputs(), [0.5, 0.5, 0.0]);
+        assert_eq!(x.max(y).backprop().wrt_inputs(), [0.5, 0.5, 0.0]);
+        assert_eq!(x.clamp(zero, y).backprop().wrt_inputs(), [0.5, 0.5, 0.0]);
+        assert_eq!(x.clamp(0.5, 1.0).backprop().wrt(x), 0.5);
+        assert_eq!(x.clamp(0.6, 1.0).backprop().wrt(x), 0.0);
+        assert_eq!(zero.hypot(zero).backprop().wrt(zero), 0.0);
+        assert_eq!(zero.atan2(zero).backprop().wrt(zero), 0.0);
+        assert_eq!(zero.powi(0).backprop().wrt(zero), 0.0);
+        assert_eq!(zero.powi(1).backprop().wrt(zero), 1.0);
+    }
+
+    #[test]
+    fn f32_min_max_etc_test() {
+        let tape = Tape::new();
+        let x = tape.new_var(1.5f32);
+        let y = tape.new_var(-0.5f32);
+        let one = tape.new_var(1.0f32);
+
+        // z = max(x, y)^2 + hypot(x, y) + atan2(y, x) + x.mul_add(y, 1)
+        let z = x.max(y).square() + x.hypot(y) + y.atan2(x) + x.mul_add(y, one);
+        let grad = z.backprop();
+
+        assert_eq!(z.val(), 1.5f32.max(-0.5).powi(2) + 1.5f32.hypot(-0.5) + (-0.5f32).atan2(1.5) + 1.5f32.mul_add(-0.5, 1.0));
+        assert_eq!(grad.wrt_inputs().iter().map(|x| (x * 1E4).round() / 1E4).collect::<Vec<f32>>(), [3.6487, 1.7838, 1.0]);
+    }
+
+    #[test]
+    fn detach_test() {
+        let tape = Tape::new();
+        let x = tape.new_var(3.0);
+        let y = tape.new_var(-2.0);
+
+        // z = x * detach(xy) + y
+        let z = x * (x * y).detach() + y;
+        let grad = z.backprop();
+
+        assert_eq!(z.val(), -20.0);
+        assert_eq!(grad.wrt_inputs(), [-6.0, 1.0]);
+    }
+
+    #[test]
+    fn stop_gradient_test() {
+        let tape = Tape::new();
+        let x = tape.new_var(1.3);
+
+        // straight-through estimator: rounds in the forward pass, acts as identity in the backward pass
+        let z = x + (x.round() - x).stop_gradient();
+        let grad = z.backprop();
+
+        assert_eq!(z.val(), 1.0);
+        assert_eq!(grad.wrt(x), 1.0);
+        assert_eq!(x.stop_gradient().powf(2.0).backprop().wrt(x), 0.0);
+    }
+
     #[test]
     fn f32_test() {
         let tape = Tape::new();
@@ -0,0 +1,66 @@
+use std::fmt::Display;
+
+use crate::network::layout::LayoutProblem;
+
+/// The errors returned by the `try_` variants of the public API, in place of panicking.
+#[derive(Debug)]
+pub enum Error {
+    /// Two lengths that must agree don't, such as a network's input layer and a sample of its input.
+    ShapeMismatch { what: &'static str, expected: usize, found: usize },
+    /// An index past the end of the layers of a layout or the neurons of a layer.
+    OutOfBounds { what: &'static str, idx: usize, len: usize },
+    EmptyDataSet,
+    /// A `Seed::Input` that is not greater than 0.
+    InvalidSeed,
+    InvalidLayout(Vec<LayoutProblem>),
+    /// A setting that is missing or out of range.
+    InvalidSetting(String),
+    Io(std::io::Error),
+    /// A saved file that couldn't be decoded.
+    Decode(String),
+}
+
+impl Display for Error {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::ShapeMismatch { what, expected, found } => write!(f, "Shape mismatch in {}: expected len {}, found {}", what, expected, found),
+            Error::OutOfBounds { what, idx, len } => write!(f, "Index {} of {} out of bounds for len {}", idx, what, len),
+            Error::EmptyDataSet => write!(f, "Data set cannot be empty"),
+            Error::InvalidSeed => write!(f, "Seed must be greater than 0"),
+            Error::InvalidLayout(problems) => {
+                let problems: Vec<String> = problems.iter().map(|problem| problem.to_string()).collect();
+                write!(f, "Invalid layout: {}", problems.join("; "))
+            },
+            Error::InvalidSetting(msg) => write!(f, "Invalid setting: {}", msg),
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::Decode(msg) => write!(f, "Decode error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    #[inline]
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    #[inline]
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// Returns an `Error::ShapeMismatch` if `found` is not `expected`.
+#[inline]
+pub(crate) fn check_len(what: &'static str, expected: usize, found: usize) -> Result<(), Error> {
+    match expected == found {
+        true => Ok(()),
+        false => Err(Error::ShapeMismatch { what, expected, found }),
+    }
+}
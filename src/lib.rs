@@ -1,4 +1,5 @@
 pub mod autodiff;
+pub mod error;
 pub mod network;
 pub mod rng;
 pub mod save_information;
@@ -6,8 +7,10 @@ pub mod training;
 pub mod tests;
 
 pub mod prelude {
-    pub use crate::network::{*, activation_fn::*, layout::*, params::*, run_results::*};
+    pub use crate::autodiff::interval::Interval;
+    pub use crate::error::Error;
+    pub use crate::network::{*, activation_fn::*, layout::*, param_views::*, params::*, run_results::*};
     pub use crate::rng::Seed;
     pub use crate::save_information::*;
-    pub use crate::training::{*, clamp_settings::*, cost::*, data_set::*, eta::*, training_settings::*};
+    pub use crate::training::{*, anomaly::*, backprop::*, clamp_settings::*, cost::*, data_set::*, eta::*, grad_clip::*, mixed_precision::*, regularization::*, training_settings::*};
 }
\ No newline at end of file
@@ -1,27 +1,31 @@
 pub mod activation_fn;
+pub mod dropout;
 pub mod layer;
 pub mod layout;
 pub mod network_builder;
-pub mod network_data;
+pub(crate) mod network_data;
+pub mod normalization;
+pub mod param_arithmetic;
+pub mod param_views;
 pub mod params;
 pub mod running;
 pub mod run_results;
+pub mod summary;
 
 use std::fmt::Display;
 use std::fs::OpenOptions;
 use std::io::Write;
 
 use crate::autodiff::real::Real;
+use crate::error::Error;
 use crate::prelude::FileNotation;
-use crate::save_information::{NetworkSaveData, SaveInformation};
+use crate::save_information::{NetworkSaveData, SavedParams, SaveInformation};
 use crate::rng::Seed;
 use crate::training::trainer::NetworkTrainer;
 
-use bitcode::Encode;
 use layout::*;
 use network_builder::NetworkBuilder;
 use params::Params;
-use serde::Serialize;
 
 #[derive(Clone, Debug, Default, PartialEq, PartialOrd)]
 pub struct Network {
@@ -56,13 +60,20 @@ impl Network {
         Params::random_params(self.layout(), seed)
     }
 
+    /// Returns an error instead of panicking if `seed` is invalid.
+    #[inline]
+    pub fn try_random_params<T: Real>(&self, seed: Seed<T>) -> Result<Params<T>, Error> {
+        Params::try_random_params(self.layout(), seed)
+    }
+
     #[inline]
     pub fn trainer<T: Real>(&self) -> NetworkTrainer<'_, T> {
         NetworkTrainer::new(self.clone())
     }
 
+    /// Saves `self` and `params` along with their precision, so `load_from_file` can load them in any precision.
     #[inline]
-    pub fn save_to_file<T: Real + Serialize + Encode>(&self, params: &Params<T>, save_info: SaveInformation) -> Result<(), std::io::Error> {
+    pub fn save_to_file<T: Real + 'static>(&self, params: &Params<T>, save_info: SaveInformation) -> Result<(), std::io::Error> {
         let mut file = OpenOptions::new()
             .write(true)
             .create_new(true)
@@ -70,7 +81,7 @@ impl Network {
 
         let net_save_data = NetworkSaveData {
             layout: self.layout().clone(),
-            params: params.clone(),
+            params: SavedParams::new(params),
         };
 
         let buf;
@@ -91,6 +102,29 @@ impl Network {
 
         Ok(())
     }
+
+    /// Loads a network and params saved with `save_to_file` in any precision, converting the params to `T`.
+    /// Also returns the largest absolute rounding error of the conversion.
+    #[inline]
+    pub fn load_from_file<T: Real>(save_info: SaveInformation) -> Result<(Self, Params<T>, f64), Error> {
+        let buf = std::fs::read(save_info.file_name())?;
+
+        let net_save_data: NetworkSaveData = if save_info.notation() == FileNotation::Binary {
+            bitcode::decode(&buf).map_err(|e| Error::Decode(e.to_string()))?
+        }
+        else {
+            let text = String::from_utf8(buf).map_err(|e| Error::Decode(e.to_string()))?;
+            match save_info.notation() {
+                FileNotation::JSON => serde_json::from_str(&text).map_err(|e| Error::Decode(e.to_string()))?,
+                FileNotation::RON => ron::from_str(&text).map_err(|e| Error::Decode(e.to_string()))?,
+                _ => toml::from_str(&text).map_err(|e| Error::Decode(e.to_string()))?,
+            }
+        };
+
+        let (params, max_error) = net_save_data.params.cast();
+
+        Ok((Network::new(net_save_data.layout), params, max_error))
+    }
 }
 
 impl Display for Network {
@@ -102,6 +136,7 @@ impl Display for Network {
 
 #[cfg(test)]
 mod tests {
+    use crate::autodiff::real::soft_float::Bf16;
     use crate::prelude::*;
 
     #[test]
@@ -130,9 +165,59 @@ mod tests {
             eta: Eta::Const(0.1),
             data_set,
             stoch_shuffle_seed: Seed::Input(5.0),
+            ..Default::default()
         }, params);
 
         let res = net.run(&input, &train_res.params());
         println!("res2: {:?}", res);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn load_from_file_test() {
+        let net = Network::builder()
+            .input_layer(2)
+            .feed_forward_layer(ActivationFn::Sigmoid, 3)
+            .dropout_layer(0.25)
+            .batch_norm_layer()
+            .feed_forward_layer(ActivationFn::Linear, 1)
+            .build();
+        let params = net.random_params::<f64>(Seed::Input(2.0));
+
+        for (notation, ext) in [(FileNotation::Binary, "bin"), (FileNotation::JSON, "json"), (FileNotation::RON, "ron"), (FileNotation::TOML, "toml")] {
+            let path = std::env::temp_dir().join(format!("neural_nets_load_test_{}.{}", std::process::id(), ext));
+            let _ = std::fs::remove_file(&path);
+            net.save_to_file(&params, SaveInformation::new(&path, notation)).unwrap();
+
+            let (loaded_net, loaded_params, max_error) = Network::load_from_file::<f64>(SaveInformation::new(&path, notation)).unwrap();
+            assert_eq!(loaded_net, net);
+            assert_eq!(max_error, 0.0);
+            assert_eq!(loaded_params.others(), params.others());
+            // text notations may round the last digit
+            for (x, y) in loaded_params.weights().iter().zip(params.weights()).chain(loaded_params.biases().iter().zip(params.biases())) {
+                assert!((x - y).abs() < 1E-15);
+            }
+
+            let (_, params_f32, max_error) = Network::load_from_file::<f32>(SaveInformation::new(&path, notation)).unwrap();
+            assert_eq!(params_f32, loaded_params.cast::<f32>());
+            assert_eq!(max_error, loaded_params.max_cast_error::<f32>());
+            assert!(max_error > 0.0 && max_error < 1E-7);
+
+            // a file saved in one precision loads in another
+            let _ = std::fs::remove_file(&path);
+            net.save_to_file(&params.cast::<f32>(), SaveInformation::new(&path, notation)).unwrap();
+            let (loaded_net, params_f64, max_error) = Network::load_from_file::<f64>(SaveInformation::new(&path, notation)).unwrap();
+            assert_eq!(loaded_net, net);
+            assert_eq!(params_f64, params.cast::<f32>().cast::<f64>());
+            assert_eq!(max_error, 0.0);
+
+            // emulated precisions are saved exactly as f64
+            let _ = std::fs::remove_file(&path);
+            net.save_to_file(&params.cast::<Bf16>(), SaveInformation::new(&path, notation)).unwrap();
+            let (_, params_bf16, max_error) = Network::load_from_file::<Bf16>(SaveInformation::new(&path, notation)).unwrap();
+            assert_eq!(params_bf16, params.cast::<Bf16>());
+            assert_eq!(max_error, 0.0);
+
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+}
@@ -2,6 +2,7 @@ use bitcode::{Decode, Encode};
 use serde::{Deserialize, Serialize};
 
 use crate::autodiff::real::{operations::OperateWithReal, real_math::RealMath, Real};
+use crate::autodiff::var::Var;
 
 /// Represents the function that returns the activation of a `Neuron`.
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Encode, Decode)]
@@ -33,6 +34,31 @@ impl ActivationFn {
         }
     }
 
+    /// Computes the derivative of the activation function at the given sum.
+    #[inline]
+    pub fn derivative<T: Real>(&self, sum: T) -> T {
+        let two = T::one() + T::one();
+        match self {
+            ActivationFn::None => T::zero(),
+            ActivationFn::Linear => T::one(),
+            ActivationFn::Sigmoid => {
+                let sigmoid = ActivationFn::sigmoid(sum);
+                sigmoid * (T::one() - sigmoid)
+            },
+            ActivationFn::Tanh => T::one() - sum.tanh() * sum.tanh(),
+            ActivationFn::ReLU => (T::one() + sum.signum()) / two,
+            ActivationFn::GELU => {
+                let sqrt_two = two.sqrt();
+                Self::cdf_nd(sum) + sum * Self::erf_derivative(sum / sqrt_two) / (two * sqrt_two)
+            },
+            ActivationFn::SiLU => {
+                let sigmoid = ActivationFn::sigmoid(sum);
+                sigmoid + sum * sigmoid * (T::one() - sigmoid)
+            },
+            ActivationFn::SmoothReLU => ActivationFn::sigmoid(sum),
+        }
+    }
+
     /// Computes the sigmoid "squishification" function.
     #[inline]
     pub fn sigmoid<'t, T: Real, U: RealMath + OperateWithReal<T>>(x: U) -> U {
@@ -96,6 +122,21 @@ impl ActivationFn {
         )
     }
 
+    /// Computes the derivative of the approximation in `erf`, so that it matches the computed values.
+    #[inline]
+    fn erf_derivative<T: Real>(x: T) -> T {
+        let c = T::from_f64;
+        let e = (-x * x).exp();
+        let root = (T::one() - e).sqrt();
+        let series = (c(32836.0) * e.powi(3) - c(93678.0) * e.powi(2) - c(5509.0) * e.powi(4) + c(272164.0) * e - c(205813.0)) / c(1935360.0) + T::one();
+        let series_derivative = 
+            (c(-3.0 * 32836.0) * e.powi(3) + c(2.0 * 93678.0) * e.powi(2) + c(4.0 * 5509.0) * e.powi(4) - c(272164.0) * e) * c(2.0) / c(1935360.0);
+        // |x| / sqrt(1 - e^(-x^2)) tends to 1 at 0
+        let ratio = if x == T::zero() { T::one() } else { x.abs() / root };
+
+        c(2.0) / c(1.77245385091) * (ratio * e * series + root * x.abs() * series_derivative)
+    }
+
     /// Computes the SmoothReLU "Softplus" activation function.
     #[inline]
     pub fn smooth_relu<'t, T: Real, U: RealMath + OperateWithReal<T>>(x: U) -> U {
@@ -103,8 +144,32 @@ impl ActivationFn {
     }    
 }
 
+/// Applies an `ActivationFn` to a whole layer at once.
+pub trait Activate<T: Real>: Sized {
+    fn activate(values: &[Self], activation_fn: ActivationFn) -> Vec<Self>;
+}
+
+impl<T: Real> Activate<T> for T {
+    #[inline]
+    fn activate(values: &[Self], activation_fn: ActivationFn) -> Vec<Self> {
+        values.iter().map(|&x| activation_fn.compute(x)).collect()
+    }
+}
+
+impl<'t, T: Real> Activate<T> for Var<'t, T> {
+    /// Records the whole layer as a single elementwise node.
+    #[inline]
+    fn activate(values: &[Self], activation_fn: ActivationFn) -> Vec<Self> {
+        match values.first() {
+            Some(var) => var.tape().map(values, |x| activation_fn.compute(x), |x| activation_fn.derivative(x)),
+            None => Vec::default(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::autodiff::tape::Tape;
     use super::*;
 
     #[test]
@@ -153,4 +218,36 @@ mod tests {
         assert!(ActivationFn::smooth_relu(f64::MAX).is_infinite());
         assert_eq!(ActivationFn::smooth_relu(0.0), 2f64.ln());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_derivative() {
+        let activation_fns = [
+            ActivationFn::None, ActivationFn::Linear, ActivationFn::Sigmoid, ActivationFn::Tanh, 
+            ActivationFn::ReLU, ActivationFn::GELU, ActivationFn::SiLU, ActivationFn::SmoothReLU,
+        ];
+
+        for activation_fn in activation_fns {
+            for x in [-2.3f64, -0.4, 0.7, 1.9] {
+                let tape = Tape::new();
+                let var = tape.new_var(x);
+                let expected = activation_fn.compute(var).backprop().wrt(var);
+
+                assert!((activation_fn.derivative(x) - expected).abs() < 1E-12, "{:?} at {}", activation_fn, x);
+            }
+        }
+        assert_eq!(ActivationFn::GELU.derivative(0.0), 0.5);
+    }
+
+    #[test]
+    fn test_activate() {
+        let tape = Tape::new();
+        let vars = tape.new_vars(&vec![-1.5, 0.5]);
+
+        let activations = Var::activate(&vars, ActivationFn::Sigmoid);
+        let sum = activations[0] * 2.0 + activations[1];
+        let grad = sum.backprop();
+
+        assert_eq!(activations.iter().map(|x| x.val()).collect::<Vec<f64>>(), f64::activate(&[-1.5, 0.5], ActivationFn::Sigmoid));
+        assert_eq!(grad.wrt_inputs(), [ActivationFn::Sigmoid.derivative(-1.5) * 2.0, ActivationFn::Sigmoid.derivative(0.5)]);
+    }
+}
@@ -0,0 +1,182 @@
+use crate::autodiff::real::{operations::OperateWithReal, Real};
+use crate::rng::{os_seed, splitmix64, Seed};
+
+use super::layer::LayerType;
+use super::network_data::NetworkData;
+use super::Network;
+
+/// The constants of SELU. Their product is the negative saturation value that alpha dropout sets dropped outputs to.
+const SELU_ALPHA: f64 = 1.6732632423543772;
+const SELU_LAMBDA: f64 = 1.0507009873554805;
+
+/// The scale and shift a dropout layer applies to each output of the layer before it in one forward pass.
+#[derive(Clone, Debug, Default, PartialEq, PartialOrd)]
+pub(crate) struct DropoutMask<T> {
+    scales: Vec<T>,
+    shifts: Vec<T>,
+}
+
+impl<T: Real> DropoutMask<T> {
+    /// Draws which of `num_neurons` outputs a layer of type `layer_type` drops, advancing `rng`.
+    #[inline]
+    pub(crate) fn new(layer_type: LayerType, num_neurons: usize, rng: &mut u64) -> Self {
+        let (rate, dropped, kept) = match layer_type {
+            LayerType::Dropout(rate) => (rate.get(), (0.0, 0.0), (1.0 / (1.0 - rate.get()), 0.0)),
+            LayerType::AlphaDropout(rate) => {
+                let rate = rate.get();
+                let saturation = -SELU_ALPHA * SELU_LAMBDA;
+                let a = ((1.0 - rate) * (1.0 + rate * saturation * saturation)).powf(-0.5);
+                let b = -a * saturation * rate;
+                (rate, (0.0, a * saturation + b), (a, b))
+            },
+            _ => (0.0, (1.0, 0.0), (1.0, 0.0)),
+        };
+
+        let mut scales = Vec::with_capacity(num_neurons);
+        let mut shifts = Vec::with_capacity(num_neurons);
+        for _ in 0..num_neurons {
+            // the top 53 bits give a uniform draw in [0, 1)
+            let draw = (splitmix64(rng) >> 11) as f64 / 2f64.powi(53);
+            let (scale, shift) = if draw < rate { dropped } else { kept };
+            scales.push(T::from_f64(scale));
+            shifts.push(T::from_f64(shift));
+        }
+
+        DropoutMask { scales, shifts }
+    }
+
+    #[inline]
+    pub(crate) fn apply<U: Copy + OperateWithReal<T>>(&self, xs: &[U]) -> Vec<U> {
+        xs.iter().zip(self.scales.iter().zip(&self.shifts)).map(|(&x, (&scale, &shift))| x * scale + shift).collect()
+    }
+
+    /// Same as `apply`, but on constants such as the network input.
+    #[inline]
+    pub(crate) fn apply_real(&self, xs: &[T]) -> Vec<T> {
+        xs.iter().zip(self.scales.iter().zip(&self.shifts)).map(|(&x, (&scale, &shift))| x * scale + shift).collect()
+    }
+}
+
+impl Network {
+    /// Returns whether any layer of `self` is a dropout layer.
+    #[inline]
+    pub fn has_dropout(&self) -> bool {
+        self.layout().layers().iter().any(|layer| layer.layer_type().is_dropout())
+    }
+
+    /// Draws a mask for each dropout layer for one forward pass, starting the generator at `state`.
+    /// There is an entry for each layer after the input layer, or none at all if `self` has no dropout layers.
+    #[inline]
+    pub(crate) fn dropout_masks<T: Real>(&self, state: u64) -> Vec<Option<DropoutMask<T>>> {
+        if !self.has_dropout() {
+            return Vec::default();
+        }
+
+        let mut rng = state;
+        NetworkData::new(self.layout().layers()).layer_data
+            .iter()
+            .map(|layer_data| {
+                let layer = layer_data.layer;
+                layer.layer_type().is_dropout().then(|| DropoutMask::new(layer.layer_type(), layer.num_neurons(), &mut rng))
+            })
+            .collect()
+    }
+}
+
+/// Resolves `seed` to the value every dropout mask of a run is drawn from. `Seed::OS` is read only here, 
+/// so a run resolves it once and can replay any of its forward passes.
+#[inline]
+pub(crate) fn dropout_seed<T: Real>(seed: Seed<T>) -> u64 {
+    let seed = match seed {
+        Seed::OS => os_seed::<f64>(),
+        Seed::Input(val) => val.to_f64(),
+    };
+
+    seed.to_bits()
+}
+
+/// Returns the state the dropout masks of forward pass `pass` of round `round` are drawn from, 
+/// such as a sample in an epoch of training, given the `seed` of the run from `dropout_seed`. 
+/// Each of `seed`, `round` and `pass` is mixed in non-linearly, so the masks of neighbouring passes and rounds are independent.
+#[inline]
+pub(crate) fn dropout_state(seed: u64, round: usize, pass: usize) -> u64 {
+    let mut state = seed;
+    state = splitmix64(&mut state) ^ round as u64;
+    state = splitmix64(&mut state) ^ pass as u64;
+    splitmix64(&mut state)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use super::*;
+    use crate::network::layer::DropoutRate;
+
+    #[test]
+    fn dropout_mask_test() {
+        let xs = vec![1.0; 10000];
+        let mut rng = 12345;
+
+        let output = DropoutMask::new(LayerType::Dropout(DropoutRate(0.25)), xs.len(), &mut rng).apply_real(&xs);
+        let num_dropped = output.iter().filter(|&&x| x == 0.0).count();
+        assert!((2300..2700).contains(&num_dropped), "{}", num_dropped);
+        assert!(output.iter().all(|&x| x == 0.0 || x == 1.0 / 0.75));
+
+        // alpha dropout keeps the mean and variance of standard normal inputs
+        let mut normal_rng = 54321;
+        let mut uniform = || ((splitmix64(&mut normal_rng) >> 11) as f64 + 1.0) / 2f64.powi(53);
+        let normals: Vec<f64> = (0..20000)
+            .map(|_| {
+                let u1 = uniform();
+                let u2 = uniform();
+                (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+            })
+            .collect();
+        let output = DropoutMask::new(LayerType::AlphaDropout(DropoutRate(0.2)), normals.len(), &mut rng).apply_real(&normals);
+        let mean = output.iter().sum::<f64>() / output.len() as f64;
+        let variance = output.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / output.len() as f64;
+        assert!(mean.abs() < 0.05 && (variance - 1.0).abs() < 0.05, "{} {}", mean, variance);
+
+        let net = Network::builder()
+            .input_layer(3)
+            .dropout_layer(0.5)
+            .feed_forward_layer(ActivationFn::ReLU, 4)
+            .alpha_dropout_layer(0.1)
+            .feed_forward_layer(ActivationFn::Linear, 1)
+            .build();
+        assert!(LayerType::Dropout(DropoutRate(0.1)) < LayerType::Dropout(DropoutRate(0.5)));
+        assert_eq!(serde_json::to_string(&LayerType::Dropout(DropoutRate(0.5))).unwrap(), r#"{"Dropout":0.5}"#);
+
+        let masks = net.dropout_masks::<f64>(7);
+        assert_eq!(masks.iter().map(Option::is_some).collect::<Vec<bool>>(), vec![true, false, true, false]);
+        assert_eq!(masks, net.dropout_masks(7));
+        assert_ne!(masks, net.dropout_masks(8));
+        assert!(Network::builder().input_layer(1).feed_forward_layer(ActivationFn::Linear, 1).build().dropout_masks::<f64>(7).is_empty());
+    }
+
+    #[test]
+    fn dropout_state_test() {
+        let net = Network::builder()
+            .input_layer(64)
+            .dropout_layer(0.5)
+            .feed_forward_layer(ActivationFn::Linear, 1)
+            .build();
+        let kept = |round, pass| -> Vec<bool> {
+            let masks = net.dropout_masks::<f64>(dropout_state(dropout_seed(Seed::Input(3.0)), round, pass));
+            masks[0].as_ref().unwrap().scales.iter().map(|&scale| scale > 0.0).collect()
+        };
+        let agreement = |pairs: Vec<(Vec<bool>, Vec<bool>)>| {
+            let num_agreeing = pairs.iter().map(|(a, b)| a.iter().zip(b).filter(|(x, y)| x == y).count()).sum::<usize>();
+            num_agreeing as f64 / (pairs.len() * 64) as f64
+        };
+
+        // independent masks agree on about half of the outputs
+        let across_passes = agreement((0..200).map(|pass| (kept(0, pass), kept(0, pass + 1))).collect());
+        let across_rounds = agreement((0..200).map(|round| (kept(round, 5), kept(round + 1, 5))).collect());
+        let across_both = agreement((0..200).map(|i| (kept(i, i + 1), kept(i + 1, i))).collect());
+        for fraction in [across_passes, across_rounds, across_both] {
+            assert!((0.47..0.53).contains(&fraction), "{} {} {}", across_passes, across_rounds, across_both);
+        }
+        assert_eq!(kept(3, 4), kept(3, 4));
+    }
+}
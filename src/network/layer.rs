@@ -1,3 +1,6 @@
+use std::cmp::Ordering;
+use std::fmt;
+
 use bitcode::{Decode, Encode};
 use serde::{Deserialize, Serialize};
 
@@ -30,6 +33,49 @@ impl Layer {
         }
     }
 
+    /// A dropout layer over the `num_neurons` outputs of the layer before it, dropping each with probability `rate`.
+    #[inline]
+    pub fn dropout(num_neurons: usize, rate: f64) -> Self {
+        Layer { 
+            layer_type: LayerType::Dropout(DropoutRate(rate)), 
+            activation_fn: ActivationFn::None, 
+            num_neurons,
+        }
+    }
+
+    /// Like `dropout`, but dropped outputs are set to the negative saturation value of SELU,
+    /// and every output is rescaled to keep the mean and variance of self-normalizing activations.
+    #[inline]
+    pub fn alpha_dropout(num_neurons: usize, rate: f64) -> Self {
+        Layer { 
+            layer_type: LayerType::AlphaDropout(DropoutRate(rate)), 
+            activation_fn: ActivationFn::None, 
+            num_neurons,
+        }
+    }
+
+    /// A batch normalization layer over the `num_neurons` outputs of the layer before it.
+    /// Each output is normalized with the mean and variance of the batch in training, and with their running averages when running.
+    #[inline]
+    pub fn batch_norm(num_neurons: usize) -> Self {
+        Layer { 
+            layer_type: LayerType::BatchNorm, 
+            activation_fn: ActivationFn::None, 
+            num_neurons,
+        }
+    }
+
+    /// A layer normalization layer over the `num_neurons` outputs of the layer before it, 
+    /// which are normalized with their own mean and variance.
+    #[inline]
+    pub fn layer_norm(num_neurons: usize) -> Self {
+        Layer { 
+            layer_type: LayerType::LayerNorm, 
+            activation_fn: ActivationFn::None, 
+            num_neurons,
+        }
+    }
+
     /// Returns the layer type of `self`.
     #[inline]
     pub fn layer_type(self) -> LayerType {
@@ -46,6 +92,17 @@ impl Layer {
     pub fn num_neurons(self) -> usize {
         self.num_neurons
     }
+
+    /// Returns the number of params of `self` stored in `Params::others`: a scale and shift for each neuron of a normalization layer,
+    /// followed by a running mean and variance for each neuron of a batch normalization layer.
+    #[inline]
+    pub fn num_others(self) -> usize {
+        match self.layer_type {
+            LayerType::BatchNorm => 4 * self.num_neurons,
+            LayerType::LayerNorm => 2 * self.num_neurons,
+            _ => 0,
+        }
+    }
 }
 
 /// The type of a `Layer`.
@@ -54,4 +111,71 @@ pub enum LayerType {
     #[default]
     Input,
     FeedForward,
-}
\ No newline at end of file
+    /// Zeroes each output of the layer before it with the given probability during training, 
+    /// scaling the rest to keep their expected value. Has no params, and passes its input through when running.
+    Dropout(DropoutRate),
+    /// Dropout for self-normalizing networks, with the given probability. See `Layer::alpha_dropout`.
+    AlphaDropout(DropoutRate),
+    /// See `Layer::batch_norm`.
+    BatchNorm,
+    /// See `Layer::layer_norm`.
+    LayerNorm,
+}
+
+impl LayerType {
+    /// Returns whether `self` is a kind of dropout, and so only has an effect during training.
+    #[inline]
+    pub fn is_dropout(self) -> bool {
+        matches!(self, LayerType::Dropout(_) | LayerType::AlphaDropout(_))
+    }
+
+    /// Returns whether `self` is a kind of normalization.
+    #[inline]
+    pub fn is_norm(self) -> bool {
+        matches!(self, LayerType::BatchNorm | LayerType::LayerNorm)
+    }
+}
+
+/// The probability with which a dropout layer drops each output. Rates are compared by their bits,
+/// so that layers have a total order.
+#[derive(Clone, Copy, Default, Serialize, Deserialize, Encode, Decode)]
+#[serde(transparent)]
+pub struct DropoutRate(pub(crate) f64);
+
+impl DropoutRate {
+    /// Returns the rate as a probability.
+    #[inline]
+    pub fn get(self) -> f64 {
+        self.0
+    }
+}
+
+impl PartialEq for DropoutRate {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for DropoutRate {}
+
+impl PartialOrd for DropoutRate {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for DropoutRate {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl fmt::Debug for DropoutRate {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
@@ -3,6 +3,8 @@ use std::fmt::Display;
 use bitcode::{Decode, Encode};
 use serde::{Deserialize, Serialize};
 
+use crate::error::Error;
+
 use super::activation_fn::ActivationFn;
 use super::layer::{Layer, LayerType};
 
@@ -60,6 +62,109 @@ impl Layout {
 
         num_biases
     }
+
+    /// Returns the number of scales, shifts and running statistics of the normalization layers.
+    #[inline]
+    pub fn num_others(&self) -> usize {
+        self.layers.iter().skip(1).map(|layer| layer.num_others()).sum()
+    }
+
+    /// Checks that `self` can be run, returning every problem found.
+    #[inline]
+    pub fn validate(&self) -> Result<(), Vec<LayoutProblem>> {
+        let problems = layout_problems(&self.layers);
+        match problems.is_empty() {
+            true => Ok(()),
+            false => Err(problems),
+        }
+    }
+}
+
+/// A reason a `Layout` can't be run, found by `Layout::validate`. Layers are indexed from the input layer.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LayoutProblem {
+    /// The first layer is not an input layer.
+    MissingInputLayer,
+    /// There is no feed-forward layer after the input layer.
+    NoOutputLayer,
+    ZeroWidth { layer: usize },
+    /// An input layer after the first layer.
+    MisplacedInputLayer { layer: usize },
+    /// A hidden feed-forward layer with `ActivationFn::None`, which always outputs zero so no later layer can learn from it.
+    /// An output layer may have no activation.
+    NoActivation { layer: usize },
+    /// A layer that can't follow the layer before it.
+    IncompatibleLayers { layer: usize, reason: String },
+    /// A dropout layer whose rate is not in `[0, 1)`.
+    InvalidDropoutRate { layer: usize },
+}
+
+impl Display for LayoutProblem {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LayoutProblem::MissingInputLayer => write!(f, "first layer must be an input layer"),
+            LayoutProblem::NoOutputLayer => write!(f, "layout must have a feed-forward layer after the input layer"),
+            LayoutProblem::ZeroWidth { layer } => write!(f, "layer {} has no neurons", layer),
+            LayoutProblem::MisplacedInputLayer { layer } => write!(f, "layer {} is an input layer, but only the first layer can be", layer),
+            LayoutProblem::NoActivation { layer } => write!(f, "layer {} has no activation function", layer),
+            LayoutProblem::IncompatibleLayers { layer, reason } => write!(f, "layer {} can't follow layer {}: {}", layer, layer - 1, reason),
+            LayoutProblem::InvalidDropoutRate { layer } => write!(f, "layer {} has a dropout rate outside [0, 1)", layer),
+        }
+    }
+}
+
+/// Returns every problem with running `layers`, in order of layer.
+#[inline]
+pub(crate) fn layout_problems(layers: &[Layer]) -> Vec<LayoutProblem> {
+    let mut problems = Vec::default();
+    match layers.first() {
+        Some(layer) if layer.layer_type == LayerType::Input => (),
+        _ => problems.push(LayoutProblem::MissingInputLayer),
+    }
+    let output_layer = layers.iter().skip(1).rposition(|layer| layer.layer_type == LayerType::FeedForward).map(|l| l + 1);
+    if output_layer.is_none() {
+        problems.push(LayoutProblem::NoOutputLayer);
+    }
+
+    for (l, layer) in layers.iter().enumerate() {
+        if layer.num_neurons == 0 {
+            problems.push(LayoutProblem::ZeroWidth { layer: l });
+        }
+        if l == 0 {
+            continue;
+        }
+
+        match layer.layer_type {
+            LayerType::Input => problems.push(LayoutProblem::MisplacedInputLayer { layer: l }),
+            LayerType::FeedForward => {
+                if layer.activation_fn == ActivationFn::None && output_layer.is_some_and(|output| l < output) {
+                    problems.push(LayoutProblem::NoActivation { layer: l });
+                }
+            },
+            LayerType::Dropout(rate) | LayerType::AlphaDropout(rate) => {
+                if !(0.0..1.0).contains(&rate.get()) {
+                    problems.push(LayoutProblem::InvalidDropoutRate { layer: l });
+                }
+                if layer.num_neurons != layers[l - 1].num_neurons {
+                    let reason = format!("dropout over {} neurons can't follow a layer of {}", layer.num_neurons, layers[l - 1].num_neurons);
+                    problems.push(LayoutProblem::IncompatibleLayers { layer: l, reason });
+                }
+            },
+            LayerType::BatchNorm | LayerType::LayerNorm => {
+                if layer.num_neurons != layers[l - 1].num_neurons {
+                    let reason = format!("normalization over {} neurons can't follow a layer of {}", layer.num_neurons, layers[l - 1].num_neurons);
+                    problems.push(LayoutProblem::IncompatibleLayers { layer: l, reason });
+                }
+                else if !layers[1..l].iter().any(|layer| layer.layer_type == LayerType::FeedForward) {
+                    let reason = "normalization must come after a feed-forward layer, so normalize the data set instead".to_string();
+                    problems.push(LayoutProblem::IncompatibleLayers { layer: l, reason });
+                }
+            },
+        }
+    }
+
+    problems
 }
 
 impl Display for Layout {
@@ -92,8 +197,131 @@ impl LayoutBuilder {
         self
     }
 
+    /// Adds a dropout layer as wide as the last layer.
+    #[inline]
+    pub fn dropout_layer(mut self, rate: f64) -> Self {
+        let num_neurons = self.layers.last().map_or(0, |layer| layer.num_neurons());
+        self.layers.push(Layer::dropout(num_neurons, rate));
+        self
+    }
+
+    /// Adds an alpha dropout layer as wide as the last layer.
+    #[inline]
+    pub fn alpha_dropout_layer(mut self, rate: f64) -> Self {
+        let num_neurons = self.layers.last().map_or(0, |layer| layer.num_neurons());
+        self.layers.push(Layer::alpha_dropout(num_neurons, rate));
+        self
+    }
+
+    /// Adds a batch normalization layer as wide as the last layer.
+    #[inline]
+    pub fn batch_norm_layer(mut self) -> Self {
+        let num_neurons = self.layers.last().map_or(0, |layer| layer.num_neurons());
+        self.layers.push(Layer::batch_norm(num_neurons));
+        self
+    }
+
+    /// Adds a layer normalization layer as wide as the last layer.
+    #[inline]
+    pub fn layer_norm_layer(mut self) -> Self {
+        let num_neurons = self.layers.last().map_or(0, |layer| layer.num_neurons());
+        self.layers.push(Layer::layer_norm(num_neurons));
+        self
+    }
+
+    /// Builds the layout, panicking if it is invalid.
     #[inline]
     pub fn build(self) -> Layout {
-        Layout { layers: self.layers }
+        self.try_build().unwrap_or_else(|e| panic!("{}", e))
     }
-}
\ No newline at end of file
+
+    /// Builds the layout, returning every problem found by `Layout::validate` if it is invalid.
+    #[inline]
+    pub fn try_build(self) -> Result<Layout, Error> {
+        let layout = Layout { layers: self.layers };
+        layout.validate().map_err(Error::InvalidLayout)?;
+
+        Ok(layout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use super::*;
+
+    #[test]
+    fn validate_test() {
+        let layout = Layout::builder()
+            .input_layer(3)
+            .feed_forward_layer(ActivationFn::ReLU, 2)
+            .feed_forward_layer(ActivationFn::Linear, 1)
+            .build();
+        assert_eq!(layout.validate(), Ok(()));
+
+        let layout = Layout::new(&vec![
+            Layer::feed_forward(2, ActivationFn::Sigmoid),
+            Layer::input(0),
+            Layer::feed_forward(2, ActivationFn::None),
+            Layer::feed_forward(1, ActivationFn::None),
+        ]);
+        // only the hidden layer is missing an activation
+        assert_eq!(layout.validate(), Err(vec![
+            LayoutProblem::MissingInputLayer,
+            LayoutProblem::ZeroWidth { layer: 1 },
+            LayoutProblem::MisplacedInputLayer { layer: 1 },
+            LayoutProblem::NoActivation { layer: 2 },
+        ]));
+
+        assert_eq!(Layout::new(&vec![]).validate(), Err(vec![LayoutProblem::MissingInputLayer, LayoutProblem::NoOutputLayer]));
+
+        let err = Layout::builder().input_layer(2).feed_forward_layer(ActivationFn::Tanh, 0).try_build().unwrap_err();
+        assert_eq!(err.to_string(), "Invalid layout: layer 1 has no neurons");
+        assert!(matches!(Network::builder().input_layer(2).try_build(), Err(Error::InvalidLayout(problems)) if problems == [LayoutProblem::NoOutputLayer]));
+
+        let layout = Layout::new(&vec![
+            Layer::input(3),
+            Layer::dropout(3, 1.0),
+            Layer::feed_forward(2, ActivationFn::ReLU),
+            Layer::alpha_dropout(3, 0.1),
+        ]);
+        assert_eq!(layout.validate(), Err(vec![
+            LayoutProblem::InvalidDropoutRate { layer: 1 },
+            LayoutProblem::IncompatibleLayers { layer: 3, reason: "dropout over 3 neurons can't follow a layer of 2".to_string() },
+        ]));
+        assert_eq!(Layout::builder().input_layer(2).dropout_layer(0.5).try_build().unwrap_err().to_string(), 
+            "Invalid layout: layout must have a feed-forward layer after the input layer");
+
+        let layout = Layout::builder()
+            .input_layer(3)
+            .dropout_layer(0.2)
+            .feed_forward_layer(ActivationFn::ReLU, 2)
+            .alpha_dropout_layer(0.1)
+            .feed_forward_layer(ActivationFn::Linear, 1)
+            .build();
+        assert_eq!(layout.layers()[3], Layer::alpha_dropout(2, 0.1));
+        assert_eq!((layout.num_weights(), layout.num_biases()), (8, 3));
+
+        let layout = Layout::new(&vec![
+            Layer::input(3),
+            Layer::batch_norm(3),
+            Layer::feed_forward(2, ActivationFn::ReLU),
+            Layer::layer_norm(4),
+        ]);
+        assert_eq!(layout.validate(), Err(vec![
+            LayoutProblem::IncompatibleLayers { layer: 1, reason: "normalization must come after a feed-forward layer, so normalize the data set instead".to_string() },
+            LayoutProblem::IncompatibleLayers { layer: 3, reason: "normalization over 4 neurons can't follow a layer of 2".to_string() },
+        ]));
+
+        let layout = Layout::builder()
+            .input_layer(3)
+            .feed_forward_layer(ActivationFn::ReLU, 2)
+            .batch_norm_layer()
+            .feed_forward_layer(ActivationFn::Linear, 1)
+            .layer_norm_layer()
+            .build();
+        assert_eq!(layout.layers()[2], Layer::batch_norm(2));
+        assert_eq!(layout.layers()[4], Layer::layer_norm(1));
+        assert_eq!((layout.num_weights(), layout.num_biases(), layout.num_others()), (8, 3, 10));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::error::Error;
 use crate::prelude::ActivationFn;
 
 use super::{layer::Layer, Layout, Network};
@@ -25,10 +26,50 @@ impl NetworkBuilder {
         self
     }
 
+    /// Adds a dropout layer as wide as the last layer.
+    #[inline]
+    pub fn dropout_layer(mut self, rate: f64) -> Self {
+        let num_neurons = self.layers.last().map_or(0, |layer| layer.num_neurons());
+        self.layers.push(Layer::dropout(num_neurons, rate));
+        self
+    }
+
+    /// Adds an alpha dropout layer as wide as the last layer.
+    #[inline]
+    pub fn alpha_dropout_layer(mut self, rate: f64) -> Self {
+        let num_neurons = self.layers.last().map_or(0, |layer| layer.num_neurons());
+        self.layers.push(Layer::alpha_dropout(num_neurons, rate));
+        self
+    }
+
+    /// Adds a batch normalization layer as wide as the last layer. Every training batch must then hold at least 2 samples.
+    #[inline]
+    pub fn batch_norm_layer(mut self) -> Self {
+        let num_neurons = self.layers.last().map_or(0, |layer| layer.num_neurons());
+        self.layers.push(Layer::batch_norm(num_neurons));
+        self
+    }
+
+    /// Adds a layer normalization layer as wide as the last layer.
+    #[inline]
+    pub fn layer_norm_layer(mut self) -> Self {
+        let num_neurons = self.layers.last().map_or(0, |layer| layer.num_neurons());
+        self.layers.push(Layer::layer_norm(num_neurons));
+        self
+    }
+
+    /// Builds the network, panicking if its layout is invalid.
     #[inline]
     pub fn build(self) -> Network {
-        Network {
-            layout: Layout { layers: self.layers },
-        }
+        self.try_build().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Builds the network, returning every problem found by `Layout::validate` if its layout is invalid.
+    #[inline]
+    pub fn try_build(self) -> Result<Network, Error> {
+        let layout = Layout { layers: self.layers };
+        layout.validate().map_err(Error::InvalidLayout)?;
+
+        Ok(Network { layout })
     }
 }
\ No newline at end of file
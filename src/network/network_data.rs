@@ -1,69 +1,161 @@
-use crate::autodiff::real::real_math::RealMath;
+use crate::error::Error;
 
-use super::{layer::{Layer, LayerType}, params::Params};
+use super::layer::{Layer, LayerType};
+use super::layout::{layout_problems, Layout};
 
 #[derive(Clone, Default, Debug, PartialEq, PartialOrd)]
-pub(super) struct NetworkData<U: RealMath> {
-    pub(super) layer_data: Vec<LayerData>,
-    pub(super) neuron_data: Vec<NeuronData<U>>,
-    pub(super) weight_data: Vec<U>,
+pub(crate) struct NetworkData {
+    pub(crate) layer_data: Vec<LayerData>,
 }
 
-impl<'t, U: RealMath> NetworkData<U> {
+impl NetworkData {
     #[inline]
-    pub(super) fn new(layers: &Vec<Layer>, params: &Params<U>) -> Self {
-        assert_eq!(layers[0].layer_type(), LayerType::Input); // first layer is input
-        assert!(layers.len() > 1); // more than one layer
-        assert!(!layers[1..].iter().any(|&x| x.layer_type != LayerType::FeedForward)); // all but the first layer are feed forward
+    pub(crate) fn new(layers: &Vec<Layer>) -> Self {
+        Self::try_new(layers).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    #[inline]
+    pub(crate) fn try_new(layers: &[Layer]) -> Result<Self, Error> {
+        let problems = layout_problems(layers);
+        if !problems.is_empty() {
+            return Err(Error::InvalidLayout(problems));
+        }
 
         let mut layer_data = Vec::with_capacity(layers.len() - 1);
-        let mut neuron_data = Vec::default();
-        let weight_data = params.weights.clone();
 
-        let mut neuron_count = 0;
         let mut weight_count = 0;
+        let mut bias_count = 0;
+        let mut other_count = 0;
         for l in 1..layers.len() {
             let neurons_in_layer = layers[l].num_neurons();
+            let weights_per_neuron = layers[l - 1].num_neurons();
 
-            layer_data.push(LayerData { layer: layers[l], neuron_start_idx: neuron_count });
+            layer_data.push(LayerData { 
+                layer: layers[l], 
+                num_inputs: weights_per_neuron,
+                weight_start_idx: weight_count, 
+                bias_start_idx: bias_count,
+                other_start_idx: other_count,
+            });
 
-            let weights_per_neuron = layers[l - 1].num_neurons();
-            for n in 0..neurons_in_layer {
-                neuron_data.push(NeuronData { activation: None, bias: params.biases[n], weight_start_idx: weight_count });
-                weight_count += weights_per_neuron;
+            // only feed-forward layers have weights and biases
+            if layers[l].layer_type() == LayerType::FeedForward {
+                weight_count += neurons_in_layer * weights_per_neuron;
+                bias_count += neurons_in_layer;
             }
-
-            neuron_count += neurons_in_layer;
+            other_count += layers[l].num_others();
         }
 
-        NetworkData {
+        Ok(NetworkData {
             layer_data,
-            neuron_data,
-            weight_data,
-        }
+        })
     }
 
+    /// Returns the layer and neuron of the param at `idx` in the weights followed by the biases and then the others of `layout`,
+    /// which is how params are laid out on a `Tape` and in a gradient.
     #[inline]
-    pub(super) fn output(&self) -> Vec<U> {
-        let last_layer = self.layer_data.last().unwrap();
-        let mut output = Vec::with_capacity(last_layer.layer.num_neurons());
-        for n in 0..last_layer.layer.num_neurons() {
-            output.push(self.neuron_data[n + last_layer.neuron_start_idx].activation.unwrap());
+    pub(crate) fn locate_param(&self, layout: &Layout, idx: usize) -> Option<(usize, usize)> {
+        let num_weights = layout.num_weights();
+        let num_params = num_weights + layout.num_biases();
+        for (l, layer_data) in self.layer_data.iter().enumerate() {
+            if layer_data.weight_range().contains(&idx) {
+                return Some((l + 1, (idx - layer_data.weight_start_idx) / layer_data.num_inputs));
+            }
+            if idx >= num_weights && layer_data.bias_range().contains(&(idx - num_weights)) {
+                return Some((l + 1, idx - num_weights - layer_data.bias_start_idx));
+            }
+            // each neuron of a normalization layer has one of each kind of other param
+            if idx >= num_params && layer_data.other_range().contains(&(idx - num_params)) {
+                return Some((l + 1, (idx - num_params - layer_data.other_start_idx) % layer_data.layer.num_neurons()));
+            }
         }
 
-        output
+        None
     }
 }
 
+/// Where the params of a layer are located. The weights of each neuron are stored contiguously, 
+/// so the weights of a layer form a row-major matrix with one row per neuron.
 #[derive(Clone, Debug, Default, PartialEq, PartialOrd)]
-pub(super) struct LayerData {
-    pub(super) layer: Layer,
-    pub(super) neuron_start_idx: usize,
+pub(crate) struct LayerData {
+    pub(crate) layer: Layer,
+    pub(crate) num_inputs: usize,
+    pub(crate) weight_start_idx: usize,
+    pub(crate) bias_start_idx: usize,
+    pub(crate) other_start_idx: usize,
+}
+
+impl LayerData {
+    #[inline]
+    pub(crate) fn weight_range(&self) -> std::ops::Range<usize> {
+        self.weight_start_idx..self.weight_start_idx + self.num_param_neurons() * self.num_inputs
+    }
+
+    #[inline]
+    pub(crate) fn bias_range(&self) -> std::ops::Range<usize> {
+        self.bias_start_idx..self.bias_start_idx + self.num_param_neurons()
+    }
+
+    #[inline]
+    pub(crate) fn other_range(&self) -> std::ops::Range<usize> {
+        self.other_start_idx..self.other_start_idx + self.layer.num_others()
+    }
+
+    /// Returns the range of the scales and shifts in `other_range`, leaving out the running statistics, which aren't trained.
+    #[inline]
+    pub(crate) fn scale_shift_range(&self) -> std::ops::Range<usize> {
+        self.other_start_idx..self.other_start_idx + self.layer.num_others().min(2 * self.layer.num_neurons())
+    }
+
+    /// Returns the number of neurons with weights and biases, which is zero for all but feed-forward layers.
+    #[inline]
+    fn num_param_neurons(&self) -> usize {
+        match self.layer.layer_type() {
+            LayerType::FeedForward => self.layer.num_neurons(),
+            _ => 0,
+        }
+    }
 }
 
-#[derive(Clone, Debug, PartialEq, PartialOrd)]
-pub(super) struct NeuronData<U: RealMath> {
-    pub(super) activation: Option<U>,
-    pub(super) bias: U,
-    pub(super) weight_start_idx: usize,
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use super::*;
+
+    #[test]
+    fn locate_param_test() {
+        let layout = Layout::builder()
+            .input_layer(3)
+            .feed_forward_layer(ActivationFn::ReLU, 2)
+            .feed_forward_layer(ActivationFn::Linear, 4)
+            .build();
+        let net_data = NetworkData::new(layout.layers());
+
+        assert_eq!(net_data.locate_param(&layout, 0), Some((1, 0)));
+        assert_eq!(net_data.locate_param(&layout, 5), Some((1, 1)));
+        assert_eq!(net_data.locate_param(&layout, 6), Some((2, 0)));
+        assert_eq!(net_data.locate_param(&layout, 13), Some((2, 3)));
+        assert_eq!(net_data.locate_param(&layout, 14), Some((1, 0)));
+        assert_eq!(net_data.locate_param(&layout, 16), Some((2, 0)));
+        assert_eq!(net_data.locate_param(&layout, 19), Some((2, 3)));
+        assert_eq!(net_data.locate_param(&layout, 20), None);
+
+        // others follow the biases, with every kind of other param of a layer counting the same neurons
+        let norm_layout = Layout::builder()
+            .input_layer(3)
+            .feed_forward_layer(ActivationFn::ReLU, 2)
+            .batch_norm_layer()
+            .feed_forward_layer(ActivationFn::Linear, 4)
+            .layer_norm_layer()
+            .build();
+        let net_data = NetworkData::new(norm_layout.layers());
+
+        assert_eq!(net_data.locate_param(&norm_layout, 19), Some((3, 3)));
+        assert_eq!(net_data.locate_param(&norm_layout, 20), Some((2, 0)));
+        assert_eq!(net_data.locate_param(&norm_layout, 23), Some((2, 1)));
+        assert_eq!(net_data.locate_param(&norm_layout, 27), Some((2, 1)));
+        assert_eq!(net_data.locate_param(&norm_layout, 28), Some((4, 0)));
+        assert_eq!(net_data.locate_param(&norm_layout, 35), Some((4, 3)));
+        assert_eq!(net_data.locate_param(&norm_layout, 36), None);
+    }
+}
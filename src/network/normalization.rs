@@ -0,0 +1,145 @@
+use crate::autodiff::real::{operations::{OperateWithReal, TensorOperations}, real_math::RealMath, Real};
+
+use super::layer::LayerType;
+use super::network_data::NetworkData;
+use super::params::Params;
+use super::Network;
+
+/// Added to each variance before it is inverted, so an output with no variance doesn't divide by zero.
+pub const NORM_EPSILON: f64 = 1E-5;
+/// The weight of the old running statistics of a batch normalization layer when they are updated with the statistics of a batch.
+pub const BATCH_NORM_MOMENTUM: f64 = 0.9;
+
+/// The mean and variance of the inputs to each batch normalization layer over a batch, by index in `NetworkData`.
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+pub(crate) struct BatchStats<U> {
+    layers: Vec<(usize, Vec<U>, Vec<U>)>,
+}
+
+impl<U> Default for BatchStats<U> {
+    #[inline]
+    fn default() -> Self {
+        BatchStats {
+            layers: Vec::default(),
+        }
+    }
+}
+
+impl<U> BatchStats<U> {
+    #[inline]
+    pub(crate) fn map<V>(self, f: impl Fn(U) -> V) -> BatchStats<V> {
+        BatchStats {
+            layers: self.layers
+                .into_iter()
+                .map(|(l, means, variances)| (l, means.into_iter().map(&f).collect(), variances.into_iter().map(&f).collect()))
+                .collect(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn extend(&mut self, other: Self) {
+        self.layers.extend(other.layers);
+    }
+}
+
+impl<T: Real> BatchStats<T> {
+    /// Moves the running mean and variance of each batch normalization layer in `params` towards the statistics of the batch.
+    #[inline]
+    pub(crate) fn update_running<M: Real>(&self, net_data: &NetworkData, params: &mut Params<M>) {
+        let momentum = M::from_f64(BATCH_NORM_MOMENTUM);
+        let update = |running: &mut M, batch: &T| *running = momentum * *running + (M::one() - momentum) * M::from_f64(batch.to_f64());
+
+        for (l, means, variances) in &self.layers {
+            let others = &mut params.others[net_data.layer_data[*l].other_range()];
+            let n = means.len();
+            others[2 * n..3 * n].iter_mut().zip(means).for_each(|(running, batch)| update(running, batch));
+            others[3 * n..].iter_mut().zip(variances).for_each(|(running, batch)| update(running, batch));
+        }
+    }
+}
+
+impl Network {
+    /// Returns whether any layer of `self` is a batch normalization layer.
+    #[inline]
+    pub fn has_batch_norm(&self) -> bool {
+        self.layout().layers().iter().any(|layer| layer.layer_type() == LayerType::BatchNorm)
+    }
+}
+
+/// Normalizes `x` with its own mean and variance, then scales and shifts it by the first two thirds of `others`.
+#[inline]
+pub(crate) fn layer_norm<T, U>(x: &[U], others: &[U]) -> Vec<U>
+where T: Real, U: RealMath + OperateWithReal<T> + TensorOperations<T>, {
+    let n = x.len();
+    let mean = U::mean(x);
+    let centered: Vec<U> = x.iter().map(|&v| v - mean).collect();
+    let variance = U::mean(&centered.iter().map(|&c| c.square()).collect::<Vec<U>>());
+    let inv_std = (variance + T::from_f64(NORM_EPSILON)).sqrt().recip();
+
+    centered.iter().zip(&others[..n]).zip(&others[n..2 * n]).map(|((&c, &scale), &shift)| c * inv_std * scale + shift).collect()
+}
+
+/// Normalizes each output of the layer before across `xs`, a batch of its outputs, then scales and shifts it by `others`.
+/// With `batch_stats`, the statistics of the batch are used and reported as layer `l`. Otherwise the running statistics are used.
+#[inline]
+pub(crate) fn batch_norm<T, U>(xs: &mut [Vec<U>], others: &[U], batch_stats: Option<(usize, &mut BatchStats<U>)>)
+where T: Real, U: RealMath + OperateWithReal<T> + TensorOperations<T>, {
+    let n = others.len() / 4;
+    let (means, variances) = match batch_stats {
+        Some((l, stats)) => {
+            let column = |j: usize, f: &dyn Fn(U) -> U| U::mean(&xs.iter().map(|x| f(x[j])).collect::<Vec<U>>());
+            let means: Vec<U> = (0..n).map(|j| column(j, &|v| v)).collect();
+            let variances: Vec<U> = (0..n).map(|j| column(j, &|v| (v - means[j]).square())).collect();
+            stats.layers.push((l, means.clone(), variances.clone()));
+            (means, variances)
+        },
+        None => (others[2 * n..3 * n].to_vec(), others[3 * n..].to_vec()),
+    };
+
+    let inv_stds: Vec<U> = variances.iter().map(|&variance| (variance + T::from_f64(NORM_EPSILON)).sqrt().recip()).collect();
+    for x in xs {
+        for (j, v) in x.iter_mut().enumerate() {
+            *v = (*v - means[j]) * inv_stds[j] * others[j] + others[n + j];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use super::*;
+
+    #[test]
+    fn normalization_test() {
+        let others = vec![2.0, 1.0, 1.0, 0.5, 0.0, -1.0];
+        let output = layer_norm::<f64, f64>(&[1.0, 2.0, 6.0], &others);
+        // mean 3, variance 14 / 3
+        let inv_std = (14.0 / 3.0 + NORM_EPSILON).sqrt().recip();
+        assert_eq!(output, vec![-2.0 * inv_std * 2.0 + 0.5, -inv_std, 3.0 * inv_std - 1.0]);
+
+        let mut xs = vec![vec![1.0, 5.0], vec![3.0, 5.0]];
+        let others = vec![1.0, 1.0, 0.0, 0.25, 10.0, 0.0, 4.0, 1.0];
+        let mut stats = BatchStats::default();
+        batch_norm::<f64, f64>(&mut xs, &others, Some((1, &mut stats)));
+        let inv_std = (1.0 + NORM_EPSILON).sqrt().recip();
+        assert_eq!(xs, vec![vec![-inv_std, 0.25], vec![inv_std, 0.25]]);
+        assert_eq!(stats.layers, vec![(1, vec![2.0, 5.0], vec![1.0, 0.0])]);
+
+        // running statistics are used when running
+        let mut xs = vec![vec![1.0, 5.0]];
+        batch_norm::<f64, f64>(&mut xs, &others, None);
+        assert_eq!(xs, vec![vec![-9.0 * (4.0 + NORM_EPSILON).sqrt().recip(), 5.0 * (1.0 + NORM_EPSILON).sqrt().recip() + 0.25]]);
+
+        let layout = Layout::builder()
+            .input_layer(1)
+            .feed_forward_layer(ActivationFn::Linear, 2)
+            .batch_norm_layer()
+            .feed_forward_layer(ActivationFn::Linear, 1)
+            .build();
+        let mut params = Params::<f64>::default_params(&layout);
+        assert_eq!(params.others(), &vec![1.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 1.0]);
+        stats.update_running(&NetworkData::new(layout.layers()), &mut params);
+        assert!((params.others()[4] - 0.2).abs() < 1E-12 && (params.others()[5] - 0.5).abs() < 1E-12);
+        assert!((params.others()[6] - 1.0).abs() < 1E-12 && (params.others()[7] - 0.9).abs() < 1E-12);
+    }
+}
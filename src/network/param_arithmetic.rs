@@ -0,0 +1,167 @@
+use std::ops::{Add, Mul, Sub};
+
+use crate::autodiff::real::Real;
+use crate::error::{check_len, Error};
+
+use super::params::Params;
+
+impl<T: Real> Params<T> {
+    /// Returns an error if `self` and `other` don't have the same number of weights, biases and others.
+    #[inline]
+    pub fn check_same_shape(&self, other: &Self) -> Result<(), Error> {
+        check_len("weights", self.weights.len(), other.weights.len())?;
+        check_len("biases", self.biases.len(), other.biases.len())?;
+        check_len("others", self.others.len(), other.others.len())
+    }
+
+    /// Applies `f` to each pair of corresponding params, panicking if the shapes don't match.
+    #[inline]
+    fn zip_map(&self, other: &Self, f: impl Fn(T, T) -> T) -> Self {
+        self.check_same_shape(other).unwrap_or_else(|e| panic!("{}", e));
+        let zip_all = |xs: &Vec<T>, ys: &Vec<T>| xs.iter().zip(ys).map(|(&x, &y)| f(x, y)).collect();
+
+        Params {
+            weights: zip_all(&self.weights, &other.weights),
+            biases: zip_all(&self.biases, &other.biases),
+            others: zip_all(&self.others, &other.others),
+        }
+    }
+
+    /// Interpolates linearly from `self` at `t = 0` to `other` at `t = 1`.
+    #[inline]
+    pub fn lerp(&self, other: &Self, t: T) -> Result<Self, Error> {
+        self.check_same_shape(other)?;
+
+        Ok(self.zip_map(other, |x, y| x + (y - x) * t))
+    }
+
+    /// Returns the sum of the products of corresponding params.
+    #[inline]
+    pub fn dot(&self, other: &Self) -> Result<T, Error> {
+        self.check_same_shape(other)?;
+
+        Ok(self.iter().zip(other.iter()).fold(T::zero(), |acc, (&x, &y)| acc + x * y))
+    }
+
+    /// Returns the L2 norm of all params.
+    #[inline]
+    pub fn norm(&self) -> T {
+        self.iter().fold(T::zero(), |acc, &x| acc + x * x).sqrt()
+    }
+
+    /// Returns the elementwise mean of `params`, such as a model soup of several checkpoints.
+    #[inline]
+    pub fn mean_of(params: &[Self]) -> Result<Self, Error> {
+        let first = params.first().ok_or_else(|| Error::InvalidSetting("cannot average zero params".to_string()))?;
+        let mut sum = first.clone();
+        for p in &params[1..] {
+            sum.check_same_shape(p)?;
+            sum = &sum + p;
+        }
+
+        Ok(sum * T::from_f64(params.len() as f64).recip())
+    }
+
+    /// Iterates over the weights, then biases, then others.
+    #[inline]
+    fn iter(&self) -> impl Iterator<Item = &T> {
+        self.weights.iter().chain(&self.biases).chain(&self.others)
+    }
+}
+
+/// Adds corresponding params, panicking if the shapes don't match.
+impl<T: Real> Add for &Params<T> {
+    type Output = Params<T>;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self::Output {
+        self.zip_map(rhs, |x, y| x + y)
+    }
+}
+
+impl<T: Real> Add for Params<T> {
+    type Output = Params<T>;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self::Output {
+        &self + &rhs
+    }
+}
+
+/// Subtracts corresponding params, panicking if the shapes don't match.
+impl<T: Real> Sub for &Params<T> {
+    type Output = Params<T>;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self::Output {
+        self.zip_map(rhs, |x, y| x - y)
+    }
+}
+
+impl<T: Real> Sub for Params<T> {
+    type Output = Params<T>;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self::Output {
+        &self - &rhs
+    }
+}
+
+impl<T: Real> Mul<T> for &Params<T> {
+    type Output = Params<T>;
+
+    #[inline]
+    fn mul(self, rhs: T) -> Self::Output {
+        let scale_all = |xs: &Vec<T>| xs.iter().map(|&x| x * rhs).collect();
+
+        Params {
+            weights: scale_all(&self.weights),
+            biases: scale_all(&self.biases),
+            others: scale_all(&self.others),
+        }
+    }
+}
+
+impl<T: Real> Mul<T> for Params<T> {
+    type Output = Params<T>;
+
+    #[inline]
+    fn mul(self, rhs: T) -> Self::Output {
+        &self * rhs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    #[test]
+    fn params_arithmetic_test() {
+        let a = Params::new(vec![1.0, 2.0], vec![3.0], vec![]);
+        let b = Params::new(vec![3.0, -2.0], vec![1.0], vec![]);
+
+        assert_eq!(&a + &b, Params::new(vec![4.0, 0.0], vec![4.0], vec![]));
+        assert_eq!(a.clone() - b.clone(), Params::new(vec![-2.0, 4.0], vec![2.0], vec![]));
+        assert_eq!(&a * 2.0, Params::new(vec![2.0, 4.0], vec![6.0], vec![]));
+        assert_eq!(a.lerp(&b, 0.25).unwrap(), Params::new(vec![1.5, 1.0], vec![2.5], vec![]));
+        assert_eq!(a.dot(&b).unwrap(), 2.0);
+        assert_eq!(a.norm(), 14f64.sqrt());
+        assert_eq!((&a - &b).norm(), 24f64.sqrt());
+
+        let c = Params::new(vec![2.0, 3.0], vec![-1.0], vec![]);
+        assert_eq!(Params::mean_of(&[a.clone(), b.clone(), c]).unwrap(), Params::new(vec![2.0, 1.0], vec![1.0], vec![]));
+        assert_eq!(Params::mean_of(&[a.clone()]).unwrap(), a);
+        assert!(matches!(Params::<f64>::mean_of(&[]), Err(Error::InvalidSetting(_))));
+
+        let other_layout = Params::new(vec![1.0], vec![1.0], vec![]);
+        assert!(matches!(a.dot(&other_layout), Err(Error::ShapeMismatch { what: "weights", expected: 2, found: 1 })));
+        assert!(a.lerp(&other_layout, 0.5).is_err());
+        assert!(Params::mean_of(&[a, other_layout]).is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn mismatched_add_test() {
+        let _ = Params::new(vec![1.0, 2.0], vec![3.0], vec![]) + Params::new(vec![1.0], vec![3.0], vec![]);
+    }
+}
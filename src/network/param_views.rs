@@ -0,0 +1,283 @@
+use crate::autodiff::real::real_math::RealMath;
+use crate::error::{check_len, Error};
+
+use super::layout::Layout;
+use super::network_data::{LayerData, NetworkData};
+use super::params::Params;
+
+/// The params of one layer: a row-major weight matrix with one row per neuron, and one bias per neuron.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct LayerParams<'p, U> {
+    weights: &'p [U],
+    biases: &'p [U],
+    num_inputs: usize,
+}
+
+/// The mutable params of one layer, laid out like `LayerParams`.
+#[derive(Debug, PartialEq, PartialOrd)]
+pub struct LayerParamsMut<'p, U> {
+    weights: &'p mut [U],
+    biases: &'p mut [U],
+    num_inputs: usize,
+}
+
+/// The params of one neuron: a weight for each input and a bias.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct NeuronParams<'p, U> {
+    weights: &'p [U],
+    bias: &'p U,
+}
+
+/// The mutable params of one neuron.
+#[derive(Debug, PartialEq, PartialOrd)]
+pub struct NeuronParamsMut<'p, U> {
+    weights: &'p mut [U],
+    bias: &'p mut U,
+}
+
+impl<'p, U> LayerParams<'p, U> {
+    #[inline]
+    pub fn weights(&self) -> &'p [U] {
+        self.weights
+    }
+
+    #[inline]
+    pub fn biases(&self) -> &'p [U] {
+        self.biases
+    }
+
+    #[inline]
+    pub fn num_inputs(&self) -> usize {
+        self.num_inputs
+    }
+
+    #[inline]
+    pub fn num_neurons(&self) -> usize {
+        self.biases.len()
+    }
+
+    /// Returns the params of neuron `n`, panicking if it is out of bounds.
+    #[inline]
+    pub fn neuron(&self, n: usize) -> NeuronParams<'p, U> {
+        NeuronParams {
+            weights: &self.weights[n * self.num_inputs..(n + 1) * self.num_inputs],
+            bias: &self.biases[n],
+        }
+    }
+}
+
+impl<U> LayerParamsMut<'_, U> {
+    #[inline]
+    pub fn weights(&self) -> &[U] {
+        self.weights
+    }
+
+    #[inline]
+    pub fn biases(&self) -> &[U] {
+        self.biases
+    }
+
+    #[inline]
+    pub fn weights_mut(&mut self) -> &mut [U] {
+        self.weights
+    }
+
+    #[inline]
+    pub fn biases_mut(&mut self) -> &mut [U] {
+        self.biases
+    }
+
+    #[inline]
+    pub fn num_inputs(&self) -> usize {
+        self.num_inputs
+    }
+
+    #[inline]
+    pub fn num_neurons(&self) -> usize {
+        self.biases.len()
+    }
+
+    /// Returns the mutable params of neuron `n`, panicking if it is out of bounds.
+    #[inline]
+    pub fn neuron_mut(&mut self, n: usize) -> NeuronParamsMut<'_, U> {
+        NeuronParamsMut {
+            weights: &mut self.weights[n * self.num_inputs..(n + 1) * self.num_inputs],
+            bias: &mut self.biases[n],
+        }
+    }
+}
+
+impl<'p, U> NeuronParams<'p, U> {
+    #[inline]
+    pub fn weights(&self) -> &'p [U] {
+        self.weights
+    }
+
+    #[inline]
+    pub fn bias(&self) -> &'p U {
+        self.bias
+    }
+}
+
+impl<U> NeuronParamsMut<'_, U> {
+    #[inline]
+    pub fn weights(&self) -> &[U] {
+        self.weights
+    }
+
+    #[inline]
+    pub fn bias(&self) -> &U {
+        self.bias
+    }
+
+    #[inline]
+    pub fn weights_mut(&mut self) -> &mut [U] {
+        self.weights
+    }
+
+    #[inline]
+    pub fn bias_mut(&mut self) -> &mut U {
+        self.bias
+    }
+}
+
+impl<U: RealMath> Params<U> {
+    /// Returns the params of layer `l` of `layout`, where layer 0 is the input layer and has no params.
+    #[inline]
+    pub fn layer(&self, layout: &Layout, l: usize) -> Result<LayerParams<'_, U>, Error> {
+        let layer_data = self.layer_data(layout, l)?;
+
+        Ok(LayerParams {
+            weights: &self.weights[layer_data.weight_range()],
+            biases: &self.biases[layer_data.bias_range()],
+            num_inputs: layer_data.num_inputs,
+        })
+    }
+
+    #[inline]
+    pub fn layer_mut(&mut self, layout: &Layout, l: usize) -> Result<LayerParamsMut<'_, U>, Error> {
+        let layer_data = self.layer_data(layout, l)?;
+
+        Ok(LayerParamsMut {
+            weights: &mut self.weights[layer_data.weight_range()],
+            biases: &mut self.biases[layer_data.bias_range()],
+            num_inputs: layer_data.num_inputs,
+        })
+    }
+
+    /// Returns the params of neuron `n` of layer `l` of `layout`.
+    #[inline]
+    pub fn neuron(&self, layout: &Layout, l: usize, n: usize) -> Result<NeuronParams<'_, U>, Error> {
+        let layer = self.layer(layout, l)?;
+        check_bounds("neuron", n, layer.num_neurons())?;
+
+        Ok(layer.neuron(n))
+    }
+
+    #[inline]
+    pub fn neuron_mut(&mut self, layout: &Layout, l: usize, n: usize) -> Result<NeuronParamsMut<'_, U>, Error> {
+        // the neuron borrows from `self` rather than from the layer view
+        let LayerParamsMut { weights, biases, num_inputs } = self.layer_mut(layout, l)?;
+        check_bounds("neuron", n, biases.len())?;
+
+        Ok(NeuronParamsMut {
+            weights: &mut weights[n * num_inputs..(n + 1) * num_inputs],
+            bias: &mut biases[n],
+        })
+    }
+
+    /// Builds params from the row-major weight matrix and biases of each layer of `layout` after the input layer,
+    /// and `others`, which holds the scales then shifts of each normalization layer in order, 
+    /// each followed by the running means then variances for batch normalization. It is empty without normalization layers.
+    #[inline]
+    pub fn from_layers(layout: &Layout, layers: Vec<(Vec<U>, Vec<U>)>, others: Vec<U>) -> Result<Self, Error> {
+        let net_data = NetworkData::try_new(layout.layers())?;
+        check_len("layers", net_data.layer_data.len(), layers.len())?;
+
+        let mut weights = Vec::with_capacity(layout.num_weights());
+        let mut biases = Vec::with_capacity(layout.num_biases());
+        for (layer_data, (layer_weights, layer_biases)) in net_data.layer_data.iter().zip(layers) {
+            check_len("layer weights", layer_data.weight_range().len(), layer_weights.len())?;
+            check_len("layer biases", layer_data.bias_range().len(), layer_biases.len())?;
+
+            weights.extend(layer_weights);
+            biases.extend(layer_biases);
+        }
+        check_len("others", layout.num_others(), others.len())?;
+
+        Ok(Params::new(weights, biases, others))
+    }
+
+    /// Checks `self` against `layout` and finds where layer `l` is.
+    #[inline]
+    fn layer_data(&self, layout: &Layout, l: usize) -> Result<LayerData, Error> {
+        let mut net_data = NetworkData::try_new(layout.layers())?;
+        check_len("weights", layout.num_weights(), self.weights.len())?;
+        check_len("biases", layout.num_biases(), self.biases.len())?;
+        if l == 0 {
+            return Err(Error::InvalidSetting("the input layer has no params".to_string()));
+        }
+        check_bounds("layer", l, layout.layers().len())?;
+
+        Ok(net_data.layer_data.swap_remove(l - 1))
+    }
+}
+
+/// Returns an `Error::OutOfBounds` if `idx` is not less than `len`.
+#[inline]
+fn check_bounds(what: &'static str, idx: usize, len: usize) -> Result<(), Error> {
+    match idx < len {
+        true => Ok(()),
+        false => Err(Error::OutOfBounds { what, idx, len }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    #[test]
+    fn layer_views_test() {
+        let layout = Layout::builder()
+            .input_layer(2)
+            .feed_forward_layer(ActivationFn::ReLU, 3)
+            .feed_forward_layer(ActivationFn::Linear, 1)
+            .build();
+        let mut params = Params::from_layers(&layout, vec![
+            (vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0], vec![0.1, 0.2, 0.3]),
+            (vec![7.0, 8.0, 9.0], vec![0.4]),
+        ], vec![]).unwrap();
+        assert_eq!(params.weights(), &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0]);
+
+        let layer = params.layer(&layout, 1).unwrap();
+        assert_eq!((layer.num_inputs(), layer.num_neurons()), (2, 3));
+        assert_eq!(layer.neuron(1).weights(), &[3.0, 4.0]);
+        assert_eq!(params.neuron(&layout, 2, 0).unwrap().weights(), &[7.0, 8.0, 9.0]);
+        assert_eq!(*params.neuron(&layout, 2, 0).unwrap().bias(), 0.4);
+
+        *params.neuron_mut(&layout, 1, 2).unwrap().bias_mut() = -1.0;
+        params.layer_mut(&layout, 2).unwrap().weights_mut()[1] = -8.0;
+        assert_eq!(params.biases(), &[0.1, 0.2, -1.0, 0.4]);
+        assert_eq!(params.layer(&layout, 2).unwrap().weights(), &[7.0, -8.0, 9.0]);
+
+        assert!(matches!(params.layer(&layout, 3), Err(Error::OutOfBounds { what: "layer", idx: 3, len: 3 })));
+        assert!(matches!(params.neuron(&layout, 1, 3), Err(Error::OutOfBounds { what: "neuron", idx: 3, len: 3 })));
+        assert!(matches!(params.layer(&layout, 0), Err(Error::InvalidSetting(_))));
+        assert!(matches!(Params::<f64>::default().layer(&layout, 1), Err(Error::ShapeMismatch { what: "weights", .. })));
+        assert!(matches!(
+            Params::from_layers(&layout, vec![(vec![1.0; 6], vec![0.0; 3]), (vec![1.0; 2], vec![0.0])], vec![]),
+            Err(Error::ShapeMismatch { what: "layer weights", expected: 3, found: 2 }),
+        ));
+
+        // normalization layers have no weights or biases, only others
+        let norm_layout = Layout::builder()
+            .input_layer(2)
+            .feed_forward_layer(ActivationFn::ReLU, 2)
+            .layer_norm_layer()
+            .build();
+        let layers = vec![(vec![1.0; 4], vec![0.0; 2]), (vec![], vec![])];
+        let norm_params = Params::from_layers(&norm_layout, layers.clone(), Params::default_params(&norm_layout).others().clone()).unwrap();
+        assert_eq!(norm_params.others(), &[1.0, 1.0, 0.0, 0.0]);
+        assert!(matches!(Params::from_layers(&norm_layout, layers, vec![]), Err(Error::ShapeMismatch { what: "others", expected: 4, found: 0 })));
+    }
+}
@@ -2,15 +2,16 @@ use bitcode::{Decode, Encode};
 use serde::{Deserialize, Serialize};
 
 use crate::autodiff::{real::{real_math::RealMath, Real}, tape::Tape, var::Var};
+use crate::error::Error;
 use crate::rng::{Seed, os_seed, lehmer_rng};
 
-use super::Layout;
+use super::{layer::LayerType, Layout};
 
 #[derive(Clone, Debug, Default, PartialEq, PartialOrd, Serialize, Deserialize, Encode, Decode)]
 pub struct Params<U: RealMath> {
     pub(super) weights: Vec<U>,
     pub(super) biases: Vec<U>,
-    pub(super) others: Vec<U>, // currently unused
+    pub(super) others: Vec<U>, // scales, shifts and running statistics of normalization layers
 }
 
 impl<U: RealMath> Params<U> {
@@ -37,6 +38,21 @@ impl<U: RealMath> Params<U> {
     pub fn others(&self) -> &Vec<U> {
         &self.others
     }
+
+    #[inline]
+    pub fn weights_mut(&mut self) -> &mut Vec<U> {
+        &mut self.weights
+    }
+
+    #[inline]
+    pub fn biases_mut(&mut self) -> &mut Vec<U> {
+        &mut self.biases
+    }
+
+    #[inline]
+    pub fn others_mut(&mut self) -> &mut Vec<U> {
+        &mut self.others
+    }
 }
 
 impl<T: Real> Params<T> {
@@ -58,26 +74,37 @@ impl<T: Real> Params<T> {
         Params {
             weights,
             biases,
-            others: Vec::default(),
+            others: Self::norm_others(layout),
         }
     }
 
     #[inline]
     pub fn random_params(layout: &Layout, seed: Seed<T>) -> Params<T> { 
-        let mut init_seed = T::one();
+        if T::EMULATED {
+            Self::random_params_in::<f64>(layout, seed)
+        }
+        else {
+            Self::random_params_in::<T>(layout, seed)
+        }
+    }
+
+    /// Generates random params with the generator running in `G`.
+    #[inline]
+    fn random_params_in<G: Real>(layout: &Layout, seed: Seed<T>) -> Params<T> { 
+        let mut init_seed = G::one();
         if seed == Seed::OS {
             init_seed = os_seed();
         }
         else if let Seed::Input(val) = seed {
             if val <= T::zero() { panic!("Seed must be greater than 0") };
 
-            init_seed = val;
+            init_seed = G::from_f64(val.to_f64());
         }
 
         let mut rng = lehmer_rng(init_seed);
 
-        let two = T::one() + T::one();
-        let two_to_the_30 = two.powf(two.powf(two + two + T::one()) - two);
+        let two = G::one() + G::one();
+        let two_to_the_30 = two.powf(two.powf(two + two + G::one()) - two);
 
         let num_weights = layout.num_weights();
         let num_biases = layout.num_biases();
@@ -85,54 +112,87 @@ impl<T: Real> Params<T> {
         let mut weight_vars = Vec::with_capacity(num_weights);
         for _ in 0..num_weights {
             rng = lehmer_rng(rng);
-            weight_vars.push(rng / two_to_the_30 - T::one());
+            weight_vars.push(T::from_f64((rng / two_to_the_30 - G::one()).to_f64()));
         }
 
         let mut bias_vars = Vec::with_capacity(num_biases);
         for _ in 0..num_biases {
             rng = lehmer_rng(rng);
-            bias_vars.push(rng / two_to_the_30 - T::one());
+            bias_vars.push(T::from_f64((rng / two_to_the_30 - G::one()).to_f64()));
         }
 
         Params { 
             weights: weight_vars, 
             biases: bias_vars, 
-            others: Vec::default() 
+            others: Self::norm_others(layout),
         }
     }
 
+    /// Returns the initial others of `layout`: a scale of 1 and shift of 0 for each normalized neuron,
+    /// and a running mean of 0 and variance of 1 for batch normalization.
     #[inline]
-    pub fn var_params<'t>(&self, tape: *const Tape<T>) -> Params<Var<'t, T>> {
-        let num_weights = self.weights().len();
-        let num_biases = self.biases().len();
-
-        let mut weights = Vec::with_capacity(num_weights);
-        for w in 0..num_weights {
-            unsafe {
-                weights.push(tape.as_ref().unwrap().new_var(self.weights()[w]));
+    fn norm_others(layout: &Layout) -> Vec<T> {
+        let mut others = Vec::with_capacity(layout.num_others());
+        for layer in layout.layers().iter().skip(1).filter(|layer| layer.layer_type().is_norm()) {
+            let n = layer.num_neurons();
+            others.extend(std::iter::repeat_n(T::one(), n));
+            others.extend(std::iter::repeat_n(T::zero(), n));
+            if layer.layer_type() == LayerType::BatchNorm {
+                others.extend(std::iter::repeat_n(T::zero(), n));
+                others.extend(std::iter::repeat_n(T::one(), n));
             }
-            
         }
 
-        let mut biases = Vec::with_capacity(num_biases);
-        for b in 0..num_biases {
-            unsafe {
-                biases.push(tape.as_ref().unwrap().new_var(self.biases()[b]));
-            }
+        others
+    }
+
+    /// Returns an error instead of panicking if `seed` is invalid.
+    #[inline]
+    pub fn try_random_params(layout: &Layout, seed: Seed<T>) -> Result<Params<T>, Error> {
+        seed.validate()?;
+
+        Ok(Self::random_params(layout, seed))
+    }
+
+    #[inline]
+    pub fn var_params<'t>(&self, tape: *const Tape<T>) -> Params<Var<'t, T>> {
+        let tape = unsafe { tape.as_ref().unwrap() };
+
+        Params {
+            weights: self.weights().iter().map(|&weight| tape.new_var(weight)).collect(),
+            biases: self.biases().iter().map(|&bias| tape.new_var(bias)).collect(),
+            others: self.others().iter().map(|&other| tape.new_var(other)).collect(),
         }
+    }
+
+    /// Converts every param to another precision by way of `f64`.
+    #[inline]
+    pub fn cast<U: Real>(&self) -> Params<U> {
+        let cast_all = |xs: &Vec<T>| xs.iter().map(|x| U::from_f64(x.to_f64())).collect();
 
         Params {
-            weights,
-            biases,
-            others: Vec::default(),
+            weights: cast_all(&self.weights),
+            biases: cast_all(&self.biases),
+            others: cast_all(&self.others),
         }
     }
+
+    /// Returns the largest absolute rounding error of `self.cast::<U>()`.
+    #[inline]
+    pub fn max_cast_error<U: Real>(&self) -> f64 {
+        self.weights
+            .iter()
+            .chain(&self.biases)
+            .chain(&self.others)
+            .map(|x| (U::from_f64(x.to_f64()).to_f64() - x.to_f64()).abs())
+            .fold(0.0, f64::max)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::prelude::*;
-    use crate::autodiff::tape::*;
+    use crate::autodiff::{real::{fixed::Q16_16, Real}, tape::*};
 
     #[test]
     fn test_default_params() {
@@ -168,6 +228,15 @@ mod tests {
         };
     
         assert_eq!(params, params2);
+
+        // f32 keeps generating in its own precision, so seeds give the same params as they always have
+        let layout = Layout::builder()
+            .input_layer(2)
+            .feed_forward_layer(ActivationFn::Linear, 2)
+            .build();
+        let params = Params::<f32>::random_params(&layout, Seed::Input(5.0));
+        assert_eq!(params.weights(), &vec![-0.14967537, -0.98046875, -0.20703125, 0.39453125]);
+        assert_eq!(params.biases(), &vec![0.421875, 0.328125]);
     }
     
     #[test]
@@ -192,4 +261,15 @@ mod tests {
         assert_ne!(var_params, var_params2);
         assert_eq!(var_params.weights.iter().map(|x| x.val()).collect::<Vec<f64>>(), var_params2.weights.iter().map(|x| x.val()).collect::<Vec<f64>>());
     }
+
+    #[test]
+    fn test_cast() {
+        let params = Params::new(vec![0.1, -2.5], vec![1E-50], vec![]);
+
+        let params_f32 = params.cast::<f32>();
+        assert_eq!(params_f32, Params::new(vec![0.1f32, -2.5], vec![0.0], vec![]));
+        assert_eq!(params.max_cast_error::<f32>(), (0.1f32 as f64 - 0.1).abs());
+        assert_eq!(params_f32.cast::<f64>().max_cast_error::<f32>(), 0.0);
+        assert_eq!(params.cast::<Q16_16>().weights()[1].to_f64(), -2.5);
+    }
 }
\ No newline at end of file
@@ -13,4 +13,31 @@ impl<T: Real, U: RealMath> RunResults<T, U> {
     pub fn output(&self) -> &Vec<U> {
         &self.output
     }
-}
\ No newline at end of file
+}
+
+/// The mean and variance of each output over several runs with dropout active, from `Network::run_mc`.
+#[derive(Clone, Debug, Default, PartialEq, PartialOrd)]
+pub struct McRunResults<T: Real> {
+    pub(super) mean: Vec<T>,
+    pub(super) variance: Vec<T>,
+}
+
+impl<T: Real> McRunResults<T> {
+    /// Returns the mean of each output, the prediction.
+    #[inline]
+    pub fn mean(&self) -> &Vec<T> {
+        &self.mean
+    }
+
+    /// Returns the population variance of each output over the runs, dividing by the number of runs rather than one less,
+    /// an estimate of the uncertainty of the prediction.
+    #[inline]
+    pub fn variance(&self) -> &Vec<T> {
+        &self.variance
+    }
+
+    #[inline]
+    pub fn std_dev(&self) -> Vec<T> {
+        self.variance.iter().map(|x| x.sqrt()).collect()
+    }
+}
@@ -1,11 +1,18 @@
-use crate::autodiff::real::operations::OperateWithReal;
+use crate::autodiff::interval::Interval;
+use crate::autodiff::real::operations::{OperateWithReal, TensorOperations};
 use crate::autodiff::real::real_math::RealMath;
 use crate::autodiff::real::Real;
+use crate::error::{check_len, Error};
 use crate::network::Network;
+use crate::rng::Seed;
 
+use super::activation_fn::Activate;
+use super::dropout::{dropout_seed, dropout_state, DropoutMask};
+use super::layer::LayerType;
 use super::network_data::NetworkData;
+use super::normalization::{batch_norm, layer_norm, BatchStats};
 use super::params::Params;
-use super::run_results::RunResults;
+use super::run_results::{McRunResults, RunResults};
 
 impl Network {
     #[inline]
@@ -13,49 +20,219 @@ impl Network {
         self.forward_pass(input, params)
     }
 
-    /// Runs `self` with the given input. Currently only works for basic feedforward networks.
+    /// Returns an error instead of panicking if the layout is invalid, or `input` or `params` are the wrong len for it.
     #[inline]
-    pub(crate) fn forward_pass<T: Real, U: RealMath + OperateWithReal<T>>(&self, input: &Vec<T>, params: &Params<U>) -> RunResults<T, U> {
-        assert_eq!(input.len(), self.layout().layers()[0].num_neurons()); // the correct number of inputs must be provided
+    pub fn try_run<T: Real + OperateWithReal<T>>(&self, input: &Vec<T>, params: &Params<T>) -> Result<RunResults<T, T>, Error> {
+        self.check_run(input, params)?;
 
-        let mut net_data = NetworkData::new(self.layout().layers(), params);
+        Ok(self.forward_pass(input, params))
+    }
 
-        // compute first layer
-        for n in 0..net_data.layer_data[0].layer.num_neurons() {
-            let mut sum = net_data.neuron_data[n].bias;
+    /// Runs `self` `n_samples` times with dropout active, as in training, for Monte Carlo dropout.
+    /// The spread of the outputs estimates how uncertain the prediction is.
+    #[inline]
+    pub fn run_mc<T: Real + OperateWithReal<T>>(&self, input: &[T], params: &Params<T>, n_samples: usize, seed: Seed<T>) -> McRunResults<T> {
+        self.try_run_mc(input, params, n_samples, seed).unwrap_or_else(|e| panic!("{}", e))
+    }
 
-            for w in 0..input.len() {
-                sum = sum + net_data.weight_data[net_data.neuron_data[n].weight_start_idx + w] * input[w];
-            }
+    /// Returns an error instead of panicking if `try_run` would, `n_samples` is 0 or `seed` is invalid.
+    #[inline]
+    pub fn try_run_mc<T: Real + OperateWithReal<T>>(&self, input: &[T], params: &Params<T>, n_samples: usize, seed: Seed<T>) -> 
+    Result<McRunResults<T>, Error> {
+        self.check_run(input, params)?;
+        if n_samples == 0 { return Err(Error::InvalidSetting("Monte Carlo dropout needs at least 1 sample".to_string())) };
+        seed.validate()?;
 
-            net_data.neuron_data[n].activation = Some(net_data.layer_data[0].layer.activation_fn().compute(sum));
+        // Welford's algorithm, which stays accurate when the variance is small next to the mean
+        let num_outputs = self.layout().layers().last().map_or(0, |layer| layer.num_neurons());
+        let mut mean = vec![T::zero(); num_outputs];
+        let mut squared_deviations = vec![T::zero(); num_outputs];
+        let seed = dropout_seed(seed);
+        for pass in 0..n_samples {
+            let masks = self.dropout_masks(dropout_state(seed, 0, pass));
+            let output = self.forward_pass_masked(input, params, &masks, |_, _, _| ()).output;
+            let k = T::from_f64((pass + 1) as f64);
+            for ((m, m2), x) in mean.iter_mut().zip(squared_deviations.iter_mut()).zip(output) {
+                let delta = x - *m;
+                *m = *m + delta / k;
+                *m2 = *m2 + delta * (x - *m);
+            }
         }
 
-        // compute all other layers
-        for l in 1..net_data.layer_data.len() {
-            for n in 0..net_data.layer_data[l].layer.num_neurons() {
-                let neuron_idx = net_data.layer_data[l].neuron_start_idx + n;
+        let n = T::from_f64(n_samples as f64);
+        let variance = squared_deviations.iter().map(|&m2| m2 / n).collect();
+
+        Ok(McRunResults { mean, variance })
+    }
+
+    #[inline]
+    fn check_run<T: Real>(&self, input: &[T], params: &Params<T>) -> Result<(), Error> {
+        self.check_params(params)?;
+        check_len("network input", self.layout().layers()[0].num_neurons(), input.len())
+    }
+
+    /// Returns an error if the layout is invalid, or `params` are the wrong len for it.
+    #[inline]
+    pub(crate) fn check_params<T: Real>(&self, params: &Params<T>) -> Result<(), Error> {
+        NetworkData::try_new(self.layout().layers())?;
+        check_len("weights", self.layout().num_weights(), params.weights.len())?;
+        check_len("biases", self.layout().num_biases(), params.biases.len())?;
+        check_len("others", self.layout().num_others(), params.others.len())
+    }
+
+    /// Runs `self` with the given input. Dropout layers pass their input through, and batch normalization uses its running statistics.
+    /// Each layer is computed with whole-layer operations, so on a `Tape` it takes only a few nodes.
+    #[inline]
+    pub(crate) fn forward_pass<T, U>(&self, input: &Vec<T>, params: &Params<U>) -> RunResults<T, U> 
+    where T: Real, U: RealMath + OperateWithReal<T> + TensorOperations<T> + Activate<T>, {
+        self.forward_pass_inspect(input, params, |_, _, _| ())
+    }
+
+    /// Runs `self` like `forward_pass`, calling `inspect` with the index, weighted sums and activations of each layer.
+    #[inline]
+    pub(crate) fn forward_pass_inspect<T, U>(&self, input: &[T], params: &Params<U>, inspect: impl FnMut(usize, &[U], &[U])) -> RunResults<T, U> 
+    where T: Real, U: RealMath + OperateWithReal<T> + TensorOperations<T> + Activate<T>, {
+        self.forward_pass_masked(input, params, &[], inspect)
+    }
+
+    /// Runs `self` like `forward_pass_inspect`, but with each dropout layer applying its mask in `masks`, as in training.
+    #[inline]
+    pub(crate) fn forward_pass_masked<T, U>(&self, input: &[T], params: &Params<U>, masks: &[Option<DropoutMask<T>>], mut inspect: impl FnMut(usize, &[U], &[U])) -> 
+    RunResults<T, U> 
+    where T: Real, U: RealMath + OperateWithReal<T> + TensorOperations<T> + Activate<T>, {
+        self.forward_batch(&[input], params, &[masks], None, |_, l, sums, activations| inspect(l, sums, activations)).swap_remove(0)
+    }
 
-                let mut sum = net_data.neuron_data[neuron_idx].bias;
+    /// Runs `self` on every input of a batch together, one layer at a time, with the dropout layers applying `masks[s]` to sample `s`.
+    /// A dropout layer without a mask passes its input through, and reports its output as both sums and activations to `inspect`,
+    /// along with the sample and layer index. Normalization layers do the same.
+    /// Batch normalization uses the statistics of the batch and reports them to `batch_stats` if it is given, 
+    /// and the running statistics in `params` otherwise.
+    #[inline]
+    pub(crate) fn forward_batch<T, U>(&self, inputs: &[&[T]], params: &Params<U>, masks: &[&[Option<DropoutMask<T>>]], 
+    batch_stats: Option<&mut BatchStats<U>>, inspect: impl FnMut(usize, usize, &[U], &[U])) -> Vec<RunResults<T, U>> 
+    where T: Real, U: RealMath + OperateWithReal<T> + TensorOperations<T> + Activate<T>, {
+        let net_data = NetworkData::new(self.layout().layers());
+        let first = first_feed_forward(&net_data);
+        let first_layer = &net_data.layer_data[first];
 
-                for w in 0..net_data.layer_data[l - 1].layer.num_neurons() {
-                    sum = sum + net_data.weight_data[net_data.neuron_data[neuron_idx].weight_start_idx + w] * 
-                        net_data.neuron_data[net_data.layer_data[l - 1].neuron_start_idx + w].activation.unwrap();
+        let products = inputs
+            .iter()
+            .enumerate()
+            .map(|(s, &input)| {
+                assert_eq!(input.len(), self.layout().layers()[0].num_neurons()); // the correct number of inputs must be provided
+
+                // dropout layers before the first feed-forward layer act on the input itself
+                let mut input = input.to_vec();
+                for mask in masks.get(s).map_or(&[][..], |&masks| masks).iter().take(first).flatten() {
+                    input = mask.apply_real(&input);
                 }
 
-                net_data.neuron_data[neuron_idx].activation = Some(net_data.layer_data[l].layer.activation_fn().compute(sum));
+                U::matmul_real(&params.weights[first_layer.weight_range()], &input, first_layer.layer.num_neurons(), 1)
+            })
+            .collect();
+
+        Self::forward_layers(&net_data, first, products, params, masks, batch_stats, inspect)
+    }
+
+    /// Runs `self` on an input of the same type as its params, such as intervals.
+    #[inline]
+    pub(crate) fn forward_pass_from<T, U>(&self, input: &[U], params: &Params<U>) -> RunResults<T, U> 
+    where T: Real, U: RealMath + OperateWithReal<T> + TensorOperations<T> + Activate<T>, {
+        assert_eq!(input.len(), self.layout().layers()[0].num_neurons()); // the correct number of inputs must be provided
+
+        let net_data = NetworkData::new(self.layout().layers());
+        let first = first_feed_forward(&net_data);
+        let first_layer = &net_data.layer_data[first];
+        let product = U::matmul(&params.weights[first_layer.weight_range()], input, first_layer.layer.num_neurons(), 1);
+
+        Self::forward_layers(&net_data, first, vec![product], params, &[], None, |_, _, _, _| ()).swap_remove(0)
+    }
+
+    /// Computes every layer from the first feed-forward layer `first` on for a batch, 
+    /// given the product of its weights and each input.
+    #[inline]
+    fn forward_layers<T, U>(net_data: &NetworkData, first: usize, products: Vec<Vec<U>>, params: &Params<U>, masks: &[&[Option<DropoutMask<T>>]], 
+    mut batch_stats: Option<&mut BatchStats<U>>, mut inspect: impl FnMut(usize, usize, &[U], &[U])) -> Vec<RunResults<T, U>> 
+    where T: Real, U: RealMath + OperateWithReal<T> + TensorOperations<T> + Activate<T>, {
+        let mut activations = products;
+        for (l, layer_data) in net_data.layer_data.iter().enumerate().skip(first) {
+            let layer = layer_data.layer;
+            match layer.layer_type() {
+                LayerType::FeedForward => {
+                    for (s, x) in activations.iter_mut().enumerate() {
+                        let product = match l > first {
+                            true => U::matmul(&params.weights[layer_data.weight_range()], x, layer.num_neurons(), 1),
+                            false => std::mem::take(x),
+                        };
+
+                        let sums = U::broadcast_add(&product, &params.biases[layer_data.bias_range()]);
+                        *x = U::activate(&sums, layer.activation_fn());
+                        inspect(s, l + 1, &sums, x);
+                    }
+                    continue;
+                },
+                LayerType::Dropout(_) | LayerType::AlphaDropout(_) => {
+                    for (s, x) in activations.iter_mut().enumerate() {
+                        if let Some(Some(mask)) = masks.get(s).and_then(|masks| masks.get(l)) {
+                            *x = mask.apply(x);
+                        }
+                    }
+                },
+                LayerType::LayerNorm => {
+                    for x in activations.iter_mut() {
+                        *x = layer_norm(x, &params.others[layer_data.other_range()]);
+                    }
+                },
+                LayerType::BatchNorm => {
+                    let stats = batch_stats.as_deref_mut().map(|stats| (l, stats));
+                    batch_norm(&mut activations, &params.others[layer_data.other_range()], stats);
+                },
+                LayerType::Input => unreachable!("only the first layer can be an input layer"),
             }
-        }
 
-        RunResults { 
-            output: net_data.output(),
-            _marker: Default::default(),
+            for (s, x) in activations.iter().enumerate() {
+                inspect(s, l + 1, x, x);
+            }
         }
+
+        activations
+            .into_iter()
+            .map(|output| RunResults { 
+                output,
+                _marker: Default::default(),
+            })
+            .collect()
     }
+
+    /// Bounds every output of `self` over all inputs inside `input_box`, using interval bound propagation.
+    /// The bounds are guaranteed but not always tight, since each neuron's interval is computed independently.
+    #[inline]
+    pub fn output_bounds<T: Real>(&self, input_box: &[Interval<T>], params: &Params<T>) -> Vec<Interval<T>> {
+        let params = Params::new(
+            params.weights.iter().map(|&x| Interval::point(x)).collect(),
+            params.biases.iter().map(|&x| Interval::point(x)).collect(),
+            params.others.iter().map(|&x| Interval::point(x)).collect(),
+        );
+
+        self.forward_pass_from(input_box, &params).output
+    }
+}
+
+/// Returns the index in `net_data` of the first feed-forward layer, which every valid layout has.
+#[inline]
+fn first_feed_forward(net_data: &NetworkData) -> usize {
+    net_data.layer_data
+        .iter()
+        .position(|layer_data| layer_data.layer.layer_type() == LayerType::FeedForward)
+        .unwrap()
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::autodiff::{interval::Interval, tape::Tape};
+    use crate::network::dropout::{dropout_seed, dropout_state};
+    use crate::network::normalization::NORM_EPSILON;
     use crate::prelude::*;
 
     #[test]
@@ -72,5 +249,164 @@ mod tests {
         let res = net.run(&input, &params);
     
         assert_eq!(res.output(), &[4.2, 4.2]);
+
+        assert_eq!(net.try_run(&input, &params).unwrap().output(), res.output());
+        assert!(matches!(net.try_run(&vec![0.5], &params), Err(Error::ShapeMismatch { what: "network input", expected: 2, found: 1 })));
+        assert!(matches!(net.try_run(&input, &Params::new(vec![1.0], vec![], vec![])), Err(Error::ShapeMismatch { .. })));
+        assert!(matches!(Network::new(Layout::new(&vec![layer::Layer::input(2)])).try_run(&input, &params), Err(Error::InvalidLayout(_))));
+    }
+
+    #[test]
+    fn test_run_with_dropout() {
+        let net = Network::builder()
+            .input_layer(2)
+            .dropout_layer(0.5)
+            .feed_forward_layer(ActivationFn::Tanh, 3)
+            .alpha_dropout_layer(0.5)
+            .feed_forward_layer(ActivationFn::Linear, 2)
+            .build();
+        let without_dropout = Network::builder()
+            .input_layer(2)
+            .feed_forward_layer(ActivationFn::Tanh, 3)
+            .feed_forward_layer(ActivationFn::Linear, 2)
+            .build();
+        let params = net.random_params::<f64>(Seed::Input(4.0));
+        let input = vec![0.3, -0.6];
+
+        // dropout only has an effect in training
+        assert_eq!(net.run(&input, &params).output(), without_dropout.run(&input, &params).output());
+
+        let masks = net.dropout_masks(11);
+        let masked = net.forward_pass_masked(&input, &params, &masks, |_, _, _| ());
+        assert_ne!(masked.output(), net.run(&input, &params).output());
+        assert_eq!(masked.output(), net.forward_pass_masked(&input, &params, &masks, |_, _, _| ()).output());
+    }
+
+    #[test]
+    fn test_run_with_normalization() {
+        let net = Network::builder()
+            .input_layer(2)
+            .feed_forward_layer(ActivationFn::Linear, 3)
+            .layer_norm_layer()
+            .feed_forward_layer(ActivationFn::Linear, 2)
+            .batch_norm_layer()
+            .build();
+        let params = net.random_params::<f64>(Seed::Input(4.0));
+        let input = vec![0.3, -0.6];
+
+        // layer normalization leaves the outputs of each sample with mean 0 and variance just under 1 before the scale and shift
+        let mut normalized = Vec::new();
+        net.forward_pass_masked(&input, &params, &[], |l, _, acts| if l == 2 { normalized = acts.to_vec() });
+        let mean = normalized.iter().sum::<f64>() / 3.0;
+        let variance = normalized.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / 3.0;
+        assert!(mean.abs() < 1E-12 && (variance - 1.0).abs() < 1E-3, "{} {}", mean, variance);
+
+        // batch normalization uses the running statistics, which start as the identity
+        let output = net.run(&input, &params).output().to_vec();
+        let mut before_norm = Vec::new();
+        net.forward_pass_masked(&input, &params, &[], |l, _, acts| if l == 3 { before_norm = acts.to_vec() });
+        for (x, y) in output.iter().zip(&before_norm) {
+            assert!((x - y / (1.0 + NORM_EPSILON).sqrt()).abs() < 1E-12);
+        }
+
+        assert!(matches!(net.try_run(&input, &Params::new(params.weights().clone(), params.biases().clone(), vec![])), 
+            Err(Error::ShapeMismatch { .. })));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_run_mc() {
+        let net = Network::builder()
+            .input_layer(2)
+            .feed_forward_layer(ActivationFn::Tanh, 16)
+            .dropout_layer(0.3)
+            .feed_forward_layer(ActivationFn::Linear, 2)
+            .build();
+        let params = net.random_params::<f64>(Seed::Input(4.0));
+        let input = vec![0.3, -0.6];
+
+        let res = net.run_mc(&input, &params, 2000, Seed::Input(9.0));
+        assert_eq!(res, net.run_mc(&input, &params, 2000, Seed::Input(9.0)));
+        assert!(res.variance().iter().all(|&x| x > 0.0));
+        // inverted dropout keeps the expected output of the linear layer
+        for ((mean, std_dev), output) in res.mean().iter().zip(res.std_dev()).zip(net.run(&input, &params).output()) {
+            assert!((mean - output).abs() < 4.0 * std_dev / 2000f64.sqrt(), "{} {}", mean, output);
+        }
+
+        // the variance stays accurate when it is tiny next to the mean, and divides by the number of runs
+        let mut offset_params = params.clone();
+        offset_params.biases_mut().iter_mut().skip(16).for_each(|b| *b += 1E8);
+        let res = net.run_mc(&input, &offset_params, 50, Seed::Input(9.0));
+        let outputs: Vec<Vec<f64>> = (0..50)
+            .map(|pass| net.forward_pass_masked(&input, &offset_params, &net.dropout_masks(dropout_state(dropout_seed(Seed::Input(9.0)), 0, pass)), |_, _, _| ()).output)
+            .collect();
+        for (i, &variance) in res.variance().iter().enumerate() {
+            let mean = outputs.iter().map(|output| output[i]).sum::<f64>() / 50.0;
+            let expected = outputs.iter().map(|output| (output[i] - mean) * (output[i] - mean)).sum::<f64>() / 50.0;
+            assert!((variance - expected).abs() < 1E-6 * expected, "{} {}", variance, expected);
+        }
+
+        // without dropout, every run is the same
+        let without_dropout = Network::builder()
+            .input_layer(2)
+            .feed_forward_layer(ActivationFn::Tanh, 16)
+            .feed_forward_layer(ActivationFn::Linear, 2)
+            .build();
+        let res = without_dropout.run_mc(&input, &params, 5, Seed::OS);
+        assert_eq!(res.variance(), &vec![0.0, 0.0]);
+        assert!((res.mean()[0] - net.run(&input, &params).output()[0]).abs() < 1E-12);
+
+        assert!(matches!(net.try_run_mc(&input, &params, 0, Seed::Input(1.0)), Err(Error::InvalidSetting(_))));
+        assert!(matches!(net.try_run_mc(&input, &params, 5, Seed::Input(-1.0)), Err(Error::InvalidSeed)));
+    }
+
+    #[test]
+    fn test_forward_pass_on_tape() {
+        let net = Network::builder()
+            .input_layer(3)
+            .feed_forward_layer(ActivationFn::Sigmoid, 4)
+            .feed_forward_layer(ActivationFn::Linear, 2)
+            .build();
+
+        let input = vec![0.5, 0.1, -0.3];
+        let params = net.random_params::<f64>(Seed::Input(3.0));
+
+        let tape = Tape::new();
+        let vars = params.var_params(&tape);
+        let num_inputs = tape.len();
+        let res = net.forward_pass(&input, &vars);
+
+        // three nodes per layer, regardless of its size
+        assert_eq!(tape.num_nodes(), num_inputs + 6);
+        assert_eq!(res.output().iter().map(|x| x.val()).collect::<Vec<f64>>(), *net.run(&input, &params).output());
+    }
+
+    #[test]
+    fn test_output_bounds() {
+        let net = Network::builder()
+            .input_layer(2)
+            .feed_forward_layer(ActivationFn::Tanh, 5)
+            .feed_forward_layer(ActivationFn::ReLU, 4)
+            .feed_forward_layer(ActivationFn::Linear, 2)
+            .build();
+        let params = net.random_params::<f64>(Seed::Input(5.0));
+
+        let input_box = [Interval::new(-0.5, 0.3), Interval::new(0.1, 0.2)];
+        let bounds = net.output_bounds(&input_box, &params);
+
+        for i in 0..=20 {
+            for j in 0..=20 {
+                let input = vec![-0.5 + 0.8 * i as f64 / 20.0, 0.1 + 0.1 * j as f64 / 20.0];
+                for (bound, output) in bounds.iter().zip(net.run(&input, &params).output()) {
+                    assert!(bound.contains(*output));
+                }
+            }
+        }
+
+        // a single point gives bounds that are tight up to rounding
+        let input = vec![0.25, -0.75];
+        let bounds = net.output_bounds(&[Interval::point(0.25), Interval::point(-0.75)], &params);
+        for (bound, output) in bounds.iter().zip(net.run(&input, &params).output()) {
+            assert!(bound.contains(*output) && bound.width() < 1E-12);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use crate::autodiff::real::Real;
+use crate::network::Network;
+
+use super::layer::LayerType;
+
+impl Network {
+    /// Returns a table of each layer's type, activation function, input and output widths and param counts,
+    /// followed by the total param count and the memory the params take up as `T`.
+    /// The params of normalization layers are counted as others.
+    #[inline]
+    pub fn summary<T: Real>(&self) -> String {
+        let header = ["Layer", "Type", "Activation", "Inputs", "Outputs", "Weights", "Biases", "Params"].map(String::from);
+        let mut rows = vec![header];
+
+        let layers = self.layout().layers();
+        let mut total_weights = 0;
+        let mut total_biases = 0;
+        let mut total_others = 0;
+        for (l, layer) in layers.iter().enumerate() {
+            let (num_inputs, num_weights, num_biases) = match (layer.layer_type(), l) {
+                (LayerType::FeedForward, 1..) => {
+                    let num_inputs = layers[l - 1].num_neurons();
+                    (num_inputs.to_string(), num_inputs * layer.num_neurons(), layer.num_neurons())
+                },
+                (LayerType::Dropout(_) | LayerType::AlphaDropout(_) | LayerType::BatchNorm | LayerType::LayerNorm, 1..) => {
+                    (layers[l - 1].num_neurons().to_string(), 0, 0)
+                },
+                _ => ("-".to_string(), 0, 0),
+            };
+            total_weights += num_weights;
+            total_biases += num_biases;
+            let num_others = layer.num_others();
+            total_others += num_others;
+
+            rows.push([
+                l.to_string(),
+                format!("{:?}", layer.layer_type()),
+                format!("{:?}", layer.activation_fn()),
+                num_inputs,
+                layer.num_neurons().to_string(),
+                num_weights.to_string(),
+                num_biases.to_string(),
+                (num_weights + num_biases + num_others).to_string(),
+            ]);
+        }
+
+        let mut widths = [0; 8];
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.len());
+            }
+        }
+
+        let mut summary = String::new();
+        for row in &rows {
+            let cells: Vec<String> = row.iter().zip(widths).map(|(cell, width)| format!("{:<width$}", cell)).collect();
+            summary += cells.join("  ").trim_end();
+            summary += "\n";
+        }
+
+        let total_params = total_weights + total_biases + total_others;
+        let type_name = std::any::type_name::<T>().rsplit("::").next().unwrap();
+        summary += &format!("Total params: {} ({} weights, {} biases", total_params, total_weights, total_biases);
+        if total_others > 0 {
+            summary += &format!(", {} others", total_others);
+        }
+        summary += ")\n";
+        summary += &format!("Memory: {} bytes as {}\n", total_params * size_of::<T>(), type_name);
+
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::autodiff::real::soft_float::Bf16;
+    use crate::prelude::*;
+
+    #[test]
+    fn summary_test() {
+        let net = Network::builder()
+            .input_layer(3)
+            .feed_forward_layer(ActivationFn::ReLU, 12)
+            .feed_forward_layer(ActivationFn::Linear, 1)
+            .build();
+
+        assert_eq!(net.summary::<f64>(), "\
+Layer  Type         Activation  Inputs  Outputs  Weights  Biases  Params
+0      Input        None        -       3        0        0       0
+1      FeedForward  ReLU        3       12       36       12      48
+2      FeedForward  Linear      12      1        12       1       13
+Total params: 61 (48 weights, 13 biases)
+Memory: 488 bytes as f64
+");
+        assert!(net.summary::<Bf16>().ends_with("Memory: 122 bytes as Bf16\n"));
+
+        let dropout_net = Network::builder()
+            .input_layer(3)
+            .dropout_layer(0.5)
+            .feed_forward_layer(ActivationFn::Linear, 1)
+            .build();
+        assert!(dropout_net.summary::<f64>().contains("\n1      Dropout(0.5)  None        3       3        0        0       0\n"));
+        assert_eq!(net.layout().num_weights() + net.layout().num_biases(), 61);
+
+        let norm_net = Network::builder()
+            .input_layer(3)
+            .feed_forward_layer(ActivationFn::ReLU, 4)
+            .batch_norm_layer()
+            .feed_forward_layer(ActivationFn::Linear, 2)
+            .layer_norm_layer()
+            .build();
+        let summary = norm_net.summary::<f32>();
+        assert!(summary.contains("\n2      BatchNorm    None        4       4        0        0       16\n"), "{}", summary);
+        assert!(summary.contains("\n4      LayerNorm    None        2       2        0        0       4\n"), "{}", summary);
+        assert!(summary.ends_with("Total params: 46 (20 weights, 6 biases, 20 others)\nMemory: 184 bytes as f32\n"), "{}", summary);
+    }
+}
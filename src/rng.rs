@@ -1,6 +1,7 @@
 use std::time::SystemTime;
 
 use crate::autodiff::real::Real;
+use crate::error::Error;
 
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Seed<T> {
@@ -9,6 +10,17 @@ pub enum Seed<T> {
     Input(T),
 }
 
+impl<T: Real> Seed<T> {
+    /// Returns an error if `self` is an input that is not greater than 0.
+    #[inline]
+    pub fn validate(&self) -> Result<(), Error> {
+        match self {
+            Seed::Input(val) if *val <= T::zero() => Err(Error::InvalidSeed),
+            _ => Ok(()),
+        }
+    }
+}
+
 #[inline]
 pub fn lehmer_rng<T: Real>(state: T) -> T {
     let one = T::one();
@@ -24,6 +36,17 @@ pub fn lehmer_rng<T: Real>(state: T) -> T {
     (num48271 * state) % num0x7fffffff
 }
 
+/// Advances `state` and returns the next output of SplitMix64. Unlike `lehmer_rng`, 
+/// neighbouring states give unrelated outputs, so it can mix several numbers into one seed.
+#[inline]
+pub(crate) fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
 #[inline]
 pub fn os_seed<T: Real>() -> T {
     let system_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_nanos();
@@ -33,14 +56,25 @@ pub fn os_seed<T: Real>() -> T {
 
 #[inline]
 pub fn shuffle<T: Real>(vec: &mut Vec<usize>, seed: Seed<T>) {
-    let mut rand = T::zero();
+    if T::EMULATED {
+        shuffle_in::<f64, T>(vec, seed);
+    }
+    else {
+        shuffle_in::<T, T>(vec, seed);
+    }
+}
+
+/// Shuffles `vec` with the generator running in `G`.
+#[inline]
+fn shuffle_in<G: Real, T: Real>(vec: &mut [usize], seed: Seed<T>) {
+    let mut rand = G::zero();
     if seed == Seed::OS {
         rand = os_seed();
     }
     else if let Seed::Input(val) = seed {
         if val <= T::zero() { panic!("Seed must be greater than 0") };
 
-        rand = val;
+        rand = G::from_f64(val.to_f64());
     }
 
     for i in 0..(vec.len() - 1) {
@@ -144,6 +178,12 @@ mod tests {
     
         assert_ne!(v1, v2);
         assert_eq!(v1.iter().fold(0, |acc, x| acc + x), v1.iter().fold(0, |acc, x| acc + x));
+
+        // f32 seeds shuffle in f32, as they always have
+        let mut v3 = v1.clone();
+        v3.push(5);
+        shuffle(&mut v3, Seed::Input(123456789f32));
+        assert_eq!(v3, vec![0, 3, 2, 1, 4, 5]);
     }
 
     #[test]
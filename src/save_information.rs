@@ -1,3 +1,4 @@
+use std::any::TypeId;
 use std::path::Path;
 
 use bitcode::{Decode, Encode};
@@ -42,7 +43,34 @@ pub enum FileNotation {
 }
 
 #[derive(Serialize, Deserialize, Encode, Decode)]
-pub(crate) struct NetworkSaveData<T: Real + Serialize> {
+pub(crate) struct NetworkSaveData {
     pub(crate) layout: Layout,
-    pub(crate) params: Params<T>,
+    pub(crate) params: SavedParams,
+}
+
+/// Saved params, tagged with their precision so they can be loaded without knowing it.
+/// Params in a precision other than `f32` are saved as `f64`, which represents all of them exactly.
+#[derive(Serialize, Deserialize, Encode, Decode)]
+pub(crate) enum SavedParams {
+    F32(Params<f32>),
+    F64(Params<f64>),
+}
+
+impl SavedParams {
+    #[inline]
+    pub(crate) fn new<T: Real + 'static>(params: &Params<T>) -> Self {
+        match TypeId::of::<T>() == TypeId::of::<f32>() {
+            true => SavedParams::F32(params.cast()),
+            false => SavedParams::F64(params.cast()),
+        }
+    }
+
+    /// Converts the params to `T`, also returning the largest absolute rounding error of the conversion.
+    #[inline]
+    pub(crate) fn cast<T: Real>(&self) -> (Params<T>, f64) {
+        match self {
+            SavedParams::F32(params) => (params.cast(), params.max_cast_error::<T>()),
+            SavedParams::F64(params) => (params.cast(), params.max_cast_error::<T>()),
+        }
+    }
 }
\ No newline at end of file
@@ -1,5 +1,5 @@
 #![cfg(test)]
-use crate::{autodiff::real::Real, prelude::*};
+use crate::{autodiff::real::{Real, fixed::Q16_16, soft_float::{Bf16, F16}}, prelude::*};
 
 // To run one of these tests:
 // cargo test <test_name> --release -- --ignored --show-output
@@ -140,4 +140,53 @@ fn polynomial_arithmetic_data_set() -> DataSet<f64> {
     }
 
     data_set.build()
-}
\ No newline at end of file
+}
+
+/// Trains the same network in `T`, returning the cost of each epoch as `f64`.
+fn train_in_precision<T: Real>() -> Vec<f64> {
+    let net = Network::builder()
+        .input_layer(2)
+        .feed_forward_layer(ActivationFn::Tanh, 4)
+        .feed_forward_layer(ActivationFn::Linear, 1)
+        .build();
+
+    let mut data_set = DataSet::builder();
+    for i in 0..8 {
+        let x = [i as f64 / 8.0, 1.0 - i as f64 / 4.0];
+        data_set = data_set.sample(x.iter().map(|&x| T::from_f64(x)).collect(), vec![T::from_f64(0.5 * x[0] - 0.3 * x[1] + 0.1)]);
+    }
+
+    let train_res = net.trainer()
+        .data_set(data_set.build())
+        // f32 generates different params from the same seed, so every precision starts from the f64 ones
+        .params(net.random_params::<f64>(Seed::Input(7.0)).cast())
+        .batch_size(4)
+        .num_epochs(30)
+        .cost_fn(CostFn::MSE)
+        .clamp_settings(ClampSettings::NO_CLAMP)
+        .eta(Eta::Const(T::from_f64(0.1)))
+        .stoch_shuffle_seed(Seed::Input(T::from_f64(3.0)))
+        .train();
+
+    train_res.avg_costs().iter().map(|x| x.iter().map(|x| x.to_f64()).sum::<f64>() / x.len() as f64).collect()
+}
+
+#[test]
+fn precision_comparison_test() {
+    let f64_costs = train_in_precision::<f64>();
+    let f32_costs = train_in_precision::<f32>();
+    let f16_costs = train_in_precision::<F16>();
+    let bf16_costs = train_in_precision::<Bf16>();
+    let fixed_costs = train_in_precision::<Q16_16>();
+
+    let max_diff = |costs: &[f64]| costs.iter().zip(&f64_costs).map(|(c, c_f64)| (c - c_f64).abs()).fold(0.0, f64::max);
+
+    // every precision trains, losing accuracy roughly in line with its precision
+    for costs in [&f32_costs, &f16_costs, &bf16_costs, &fixed_costs] {
+        assert!(costs.last().unwrap() < &1E-3);
+    }
+    assert!(max_diff(&f32_costs) < 1E-6);
+    assert!(max_diff(&fixed_costs) < 1E-4);
+    assert!(max_diff(&f16_costs) < 1E-3);
+    assert!(max_diff(&bf16_costs) < 1E-2);
+}
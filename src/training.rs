@@ -1,29 +1,67 @@
+pub mod anomaly;
+pub mod backprop;
 pub mod clamp_settings;
 pub mod cost;
 pub mod data_set;
 pub mod eta;
+pub mod grad_clip;
+pub mod gradient_check;
+pub mod mixed_precision;
+pub mod regularization;
 pub mod trainer;
 pub mod training_results;
 pub mod training_settings;
 
-use crate::autodiff::{real::Real, tape::Tape, var::Var};
-use crate::network::{Network, params::Params};
+use crate::autodiff::{real::Real, tape::{Tape, TapeAnomaly}, var::Var};
+use crate::network::{Network, dropout::{dropout_seed, dropout_state, DropoutMask}, network_data::NetworkData, normalization::BatchStats, params::Params};
 use crate::rng::{i64_to_real, shuffle};
 
+use anomaly::{Anomaly, AnomalyMode, AnomalySource};
+use backprop::Backend;
+use clamp_settings::ClampSettings;
 use eta::Eta;
 use training_results::TrainingResults;
 use training_settings::TrainingSettings;
 
+/// What training on one batch produced. `params` are the updated params.
+struct BatchOutput<T: Real> {
+    params: Params<T>,
+    costs: Vec<T>,
+    avg_cost: T,
+    grad: Vec<T>,
+    penalty: T,
+    clipped: bool,
+    anomaly: Option<Anomaly>,
+}
+
 impl Network {    
     /// Runs `self` with the given input and adjusts params to minimize cost.
     #[inline]
-    pub fn train<'t, T>(&self, settings: &TrainingSettings<'t, T>, mut params: Params<T>) -> TrainingResults<T> 
+    pub fn train<'t, T>(&self, settings: &TrainingSettings<'t, T>, params: Params<T>) -> TrainingResults<T> 
     where T: Real, {
+        self.train_loop(settings, params, |params, batch_samples, seed, e, b| self.per_batch(settings, batch_samples, params, seed, e, b))
+    }
+
+    /// Runs the epochs and batches of training, with `step` computing the costs and gradient of each batch and updating the params.
+    /// `step` is also given the dropout seed of the run, which `Seed::OS` is resolved to once.
+    #[inline]
+    fn train_loop<'t, T, M, F>(&self, settings: &TrainingSettings<'t, T>, mut params: Params<M>, mut step: F) -> TrainingResults<M> 
+    where T: Real, M: Real, F: FnMut(&Params<M>, &[usize], u64, usize, usize) -> BatchOutput<M>, {
         assert!(settings.batch_size <= settings.data_set.len());
 
         let mut all_costs = Vec::default();
         let mut avg_costs = Vec::default();
         let mut all_grads = Vec::default();
+        let mut penalties = Vec::default();
+        let mut clip_counts = Vec::default();
+        let mut anomalies = Vec::default();
+        let mut aborted = false;
+        let seed = dropout_seed(settings.stoch_shuffle_seed);
+
+        let ema_decay = settings.ema_decay.map(|decay| M::from_f64(decay.to_f64()));
+        let mut ema_params = ema_decay.map(|_| params.clone());
+        let mut swa_params: Option<Params<M>> = None;
+        let mut num_swa_epochs = 0;
         for e in 0..settings.num_epochs {
             // list of indices is randomly shuffled to provide randomness for stochastic batching
             let mut samples: Vec<usize> = (0..settings.data_set.len()).collect();
@@ -32,6 +70,8 @@ impl Network {
             let mut costs_in_epoch = Vec::default();
             let mut avg_costs_in_epoch = Vec::default();
             let mut grads_in_epoch = Vec::default();
+            let mut penalties_in_epoch = Vec::default();
+            let mut clips_in_epoch = 0;
             for b in 0..settings.num_batches() {
                 // for cases in which length of data set is not divisible by batch size,
                 // the final batch needs to be reduced size
@@ -42,19 +82,51 @@ impl Network {
                 else {
                     batch_size = settings.batch_size;
                 }
+                let start = b * settings.batch_size;
 
                 // here is where the training logic actually happens
-                let output = self.per_batch(settings, &samples, &params, e, b, batch_size);
+                let output = step(&params, &samples[start..start + batch_size], seed, e, b);
+
+                // the update from a batch with an anomaly is discarded
+                match output.anomaly {
+                    Some(anomaly) => {
+                        anomalies.push(anomaly);
+                        aborted = settings.anomaly_mode == AnomalyMode::Abort;
+                    },
+                    None => {
+                        params = output.params;
+                        if let (Some(ema), Some(decay)) = (ema_params.as_mut(), ema_decay) {
+                            *ema = &(&*ema * decay) + &(&params * (M::one() - decay));
+                        }
+                    },
+                }
+                costs_in_epoch.push(output.costs);
+                avg_costs_in_epoch.push(output.avg_cost);
+                grads_in_epoch.push(output.grad);
+                penalties_in_epoch.push(output.penalty);
+                clips_in_epoch += output.clipped as usize;
 
-                params = output.0;
-                costs_in_epoch.push(output.1);
-                avg_costs_in_epoch.push(output.2);
-                grads_in_epoch.push(output.3);
+                if aborted {
+                    break;
+                }
             }
 
             all_costs.push(costs_in_epoch);
             avg_costs.push(avg_costs_in_epoch);
             all_grads.push(grads_in_epoch);
+            penalties.push(penalties_in_epoch);
+            clip_counts.push(clips_in_epoch);
+
+            if aborted {
+                break;
+            }
+
+            if settings.swa_start_epoch.is_some_and(|start| e >= start) {
+                // running mean of the params at the end of each epoch
+                num_swa_epochs += 1;
+                let swa = swa_params.get_or_insert_with(|| params.clone());
+                *swa = &*swa + &((&params - &*swa) * M::from_f64(num_swa_epochs as f64).recip());
+            }
         }
 
         TrainingResults {
@@ -63,53 +135,207 @@ impl Network {
             all_costs,
             avg_costs,
             all_grads,
+            penalties,
+            clip_counts,
+            anomalies,
+            ema_params,
+            swa_params,
         }
     }
 
     #[inline]
-    fn per_batch<'t, T>(&self, settings: &TrainingSettings<'t, T>, samples: &Vec<usize>, params: &Params<T>, e: usize, b: usize, b_size: usize) -> 
-    (Params<T>, Vec<T>, T, Vec<T>)
+    fn per_batch<'t, T>(&self, settings: &TrainingSettings<'t, T>, batch_samples: &[usize], params: &Params<T>, seed: u64, e: usize, b: usize) -> 
+    BatchOutput<T>
+    where T: Real, {
+        let scale = i64_to_real::<T>(batch_samples.len() as i64).recip();
+        let (costs, mut grad, tape_anomaly, batch_stats) = self.batch_gradient(settings, batch_samples, params, scale, seed, e);
+        let avg_cost = costs.iter().fold(T::zero(), |acc, &cost| acc + cost) * scale;
+        let penalty = settings.regularization.apply(self.layout(), params, &mut grad, T::one());
+        let clipped = settings.grad_clip.apply(self.layout(), &mut grad);
+
+        let mut new_params = Self::adjust_params(&grad, settings, settings.eta(), e, params);
+        batch_stats.update_running(&NetworkData::new(self.layout().layers()), &mut new_params);
+
+        let anomaly = match settings.anomaly_mode != AnomalyMode::Off {
+            true => self.find_anomaly(settings, batch_samples, params, (tape_anomaly, &costs, &grad, &new_params), (seed, e, b)),
+            false => None,
+        };
+
+        BatchOutput { params: new_params, costs, avg_cost, grad, penalty, clipped, anomaly }
+    }
+
+    /// Computes the costs of `batch_samples` in epoch `e` and the gradient of their sum times `scale`, 
+    /// splitting the batch across `settings.num_threads` worker threads, along with the statistics of any batch normalization layers.
+    #[inline]
+    fn batch_gradient<'t, T>(&self, settings: &TrainingSettings<'t, T>, batch_samples: &[usize], params: &Params<T>, scale: T, seed: u64, e: usize) -> 
+    (Vec<T>, Vec<T>, Option<TapeAnomaly>, BatchStats<T>)
     where T: Real, {
+        // each worker thread computes the gradient of its share of the batch on its own tape,
+        // unless batch normalization needs the whole batch on one tape
+        let chunks = if settings.num_threads > 1 && !self.has_batch_norm() {
+            let chunk_len = batch_samples.len().div_ceil(settings.num_threads);
+            std::thread::scope(|scope| {
+                let handles: Vec<_> = batch_samples
+                    .chunks(chunk_len)
+                    .map(|chunk| scope.spawn(move || self.chunk_gradient(settings, chunk, params, scale, seed, e)))
+                    .collect();
+
+                handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+            })
+        }
+        else {
+            vec![self.chunk_gradient(settings, batch_samples, params, scale, seed, e)]
+        };
+
+        let mut costs = Vec::with_capacity(batch_samples.len());
+        let mut grad = vec![T::zero(); params.weights().len() + params.biases().len() + params.others().len()];
+        let mut tape_anomaly = None;
+        let mut batch_stats = BatchStats::default();
+        for (chunk_costs, chunk_grad, chunk_anomaly, chunk_stats) in chunks {
+            costs.extend(chunk_costs);
+            for (g, chunk_g) in grad.iter_mut().zip(chunk_grad) {
+                *g = *g + chunk_g;
+            }
+            tape_anomaly = tape_anomaly.or(chunk_anomaly);
+            batch_stats.extend(chunk_stats);
+        }
+
+        (costs, grad, tape_anomaly, batch_stats)
+    }
+
+    /// Computes the costs of `chunk` in epoch `e` and the gradient of their sum times `scale`.
+    #[inline]
+    fn chunk_gradient<'t, T>(&self, settings: &TrainingSettings<'t, T>, chunk: &[usize], params: &Params<T>, scale: T, seed: u64, e: usize) -> 
+    (Vec<T>, Vec<T>, Option<TapeAnomaly>, BatchStats<T>)
+    where T: Real, {
+        if settings.backend == Backend::Analytic && settings.anomaly_mode == AnomalyMode::Off {
+            if let Some((costs, grad)) = self.analytic_gradient(settings, chunk, params, scale) {
+                return (costs, grad, None, BatchStats::default());
+            }
+        }
+
         let tape = Tape::new();
+        tape.set_anomaly_mode(settings.anomaly_mode != AnomalyMode::Off);
         let vars = params.var_params(&tape);
 
-        let costs = self.get_costs(settings, samples, &vars, b, b_size);
+        let (costs, batch_stats) = self.get_costs(settings, chunk, &vars, seed, e);
 
         // combine costs before backprop
-        let mut total_cost = costs[0];
-        for cost in costs[1..].iter() {
-            total_cost = total_cost + *cost;
-        }
+        let chunk_cost = tape.sum(&costs) * scale;
 
-        let avg_cost = total_cost / i64_to_real::<T>(b_size as i64);
-
-        let full_gradient = avg_cost.backprop();
+        let full_gradient = chunk_cost.backprop();
         let grad = full_gradient.wrt_inputs();
 
-        let new_params = Self::adjust_params(grad, settings, settings.eta(), e, &params);
-
-        (new_params, costs.iter().map(|x| x.val()).collect(), avg_cost.val(), grad.to_vec())
+        (costs.iter().map(|x| x.val()).collect(), grad.to_vec(), tape.anomaly(), batch_stats)
     }
 
+    /// Computes the cost of each of `samples` in epoch `e` as one batch, with a dropout mask drawn for each sample,
+    /// and returns the statistics of any batch normalization layers.
     #[inline]
-    fn get_costs<'t, T>(&self, settings: &TrainingSettings<'t, T>, samples: &Vec<usize>, vars: &Params<Var<'t, T>>, b: usize, b_size: usize) -> 
-    Vec<Var<'t, T>> 
+    fn get_costs<'t, T>(&self, settings: &TrainingSettings<'t, T>, samples: &[usize], vars: &Params<Var<'t, T>>, seed: u64, e: usize) -> 
+    (Vec<Var<'t, T>>, BatchStats<T>) 
     where T: Real, {
-        let mut costs = Vec::with_capacity(settings.num_epochs);
-        for s in 0..b_size {
-            let sample_idx = samples[b + s];
-            let mut res = self.forward_pass(&settings.data_set.nth_input(sample_idx).to_vec(), vars);
-    
-            let cost = res.cost(settings.cost_fn(), &settings.data_set.nth_output(sample_idx).to_vec());
-            costs.push(cost);
+        let inputs: Vec<&[T]> = samples.iter().map(|&sample_idx| settings.data_set.nth_input(sample_idx)).collect();
+        let masks = self.batch_masks(samples, seed, e);
+        let masks: Vec<&[_]> = masks.iter().map(Vec::as_slice).collect();
+
+        let mut batch_stats = BatchStats::default();
+        let results = self.forward_batch(&inputs, vars, &masks, Some(&mut batch_stats), |_, _, _, _| ());
+
+        let costs = results
+            .into_iter()
+            .zip(samples)
+            .map(|(mut res, &sample_idx)| res.cost(settings.cost_fn(), &settings.data_set.nth_output(sample_idx).to_vec()))
+            .collect();
+
+        (costs, batch_stats.map(|x| x.val()))
+    }
+
+    /// Draws the dropout masks of each of `samples` in epoch `e` of a run with dropout seed `seed`. 
+    /// The masks depend only on the seed, epoch and sample, and not on how the batch is split across threads.
+    #[inline]
+    fn batch_masks<T: Real>(&self, samples: &[usize], seed: u64, e: usize) -> Vec<Vec<Option<DropoutMask<T>>>> {
+        samples
+            .iter()
+            .map(|&sample_idx| self.dropout_masks(dropout_state(seed, e, sample_idx)))
+            .collect()
+    }
+
+    /// Finds where the first non-finite value of a batch appeared, checking the tape, then the gradient, then the updated params.
+    /// A non-finite value in the forward pass is located by replaying the batch with the same dropout masks and batch statistics,
+    /// and is reported at the earliest layer it appears in.
+    #[inline]
+    fn find_anomaly<'t, T, P>(&self, settings: &TrainingSettings<'t, T>, batch_samples: &[usize], params: &Params<T>, 
+    (tape_anomaly, costs, grad, new_params): (Option<TapeAnomaly>, &[T], &[T], &Params<P>), (seed, e, b): (u64, usize, usize)) -> 
+    Option<Anomaly>
+    where T: Real, P: Real, {
+        let net_data = NetworkData::new(self.layout().layers());
+        let anomaly = |source, sample, location: Option<(usize, usize)>| Anomaly { 
+            epoch: e, 
+            batch: b, 
+            source, 
+            sample, 
+            layer: location.map(|(layer, _)| layer), 
+            neuron: location.map(|(_, neuron)| neuron),
+        };
+
+        if let Some(tape_anomaly) = tape_anomaly {
+            let source = AnomalySource::Tape { op: tape_anomaly.name().to_string(), partial: tape_anomaly.partial() };
+
+            // params are the first values on the tape
+            if tape_anomaly.index() < grad.len() {
+                return Some(anomaly(source, None, net_data.locate_param(self.layout(), tape_anomaly.index())));
+            }
+
+            let inputs: Vec<&[T]> = batch_samples.iter().map(|&sample_idx| settings.data_set.nth_input(sample_idx)).collect();
+            let masks = self.batch_masks(batch_samples, seed, e);
+            let masks: Vec<&[_]> = masks.iter().map(Vec::as_slice).collect();
+
+            let mut locations = vec![None; batch_samples.len()];
+            self.forward_batch(&inputs, params, &masks, Some(&mut BatchStats::default()), |s, l, sums, activations| {
+                if locations[s].is_none() {
+                    locations[s] = sums
+                        .iter()
+                        .zip(activations)
+                        .position(|(sum, activation)| !sum.is_finite() || !activation.is_finite())
+                        .map(|n| (l, n));
+                }
+            });
+
+            // batch normalization spreads a non-finite value to every sample, so the earliest layer finds where it started
+            let first = locations.iter().enumerate().filter_map(|(s, location)| location.map(|(l, _)| (l, s))).min();
+            if let Some((_, s)) = first {
+                return Some(anomaly(source, Some(batch_samples[s]), locations[s]));
+            }
+
+            // the forward pass was finite, so the cost was not
+            let sample = costs.iter().position(|cost| !cost.is_finite()).map(|s| batch_samples[s]);
+            return Some(anomaly(source, sample, None));
+        }
+
+        if let Some(idx) = grad.iter().position(|x| !x.is_finite()) {
+            return Some(anomaly(AnomalySource::Gradient, None, net_data.locate_param(self.layout(), idx)));
         }
 
-        costs
+        new_params.weights()
+            .iter()
+            .chain(new_params.biases())
+            .chain(new_params.others())
+            .position(|x| !x.is_finite())
+            .map(|idx| anomaly(AnomalySource::Param, None, net_data.locate_param(self.layout(), idx)))
     }
 
     /// Adjusts weights and biases according to grad. KNOWN PROBLEM: Large eta value
     #[inline]
     fn adjust_params<'t, T>(grad: &[T], settings: &TrainingSettings<'t, T>, eta: &Eta<T>, epoch: usize, params: &Params<T>) -> Params<T> 
+    where T: Real, {
+        let eta_val = eta.val(epoch, settings.num_epochs);
+        Self::update_params(grad, &settings.clamp_settings, eta_val, settings.regularization.weight_decay, params)
+    }
+
+    /// Takes a step of size `eta_val` against `grad` with decoupled weight decay, clamping the new weights and biases.
+    #[inline]
+    fn update_params<T>(grad: &[T], clamp_settings: &ClampSettings<T>, eta_val: T, weight_decay: T, params: &Params<T>) -> Params<T> 
     where T: Real, {
         let weights_len = params.weights().len();
         let mut new_weights = Vec::with_capacity(weights_len);
@@ -117,22 +343,27 @@ impl Network {
         let biases_len = params.biases().len();
         let mut new_biases = Vec::with_capacity(biases_len);
 
-        let eta_val = eta.val(epoch, settings.num_epochs);
         for w in 0..weights_len {
-            let weight = params.weights()[w] - eta_val * grad[w];
-            let weight = weight.clamp(settings.weight_min(), settings.weight_max());
+            let weight = params.weights()[w] - eta_val * grad[w] - eta_val * weight_decay * params.weights()[w];
+            let weight = weight.clamp(clamp_settings.weight_min(), clamp_settings.weight_max());
 
             new_weights.push(weight);
         }
         for b in 0..biases_len {
             let bias = params.biases()[b] - eta_val * grad[weights_len + b];
-            let bias = bias.clamp(settings.bias_min(), settings.bias_max());
+            let bias = bias.clamp(clamp_settings.bias_min(), clamp_settings.bias_max());
 
             new_biases.push(bias);
         }
 
-        // others not implemented
-        Params::new(new_weights, new_biases, Vec::default())
+        // running statistics have no gradient, so only the scales and shifts of normalization layers move
+        let new_others = params.others()
+            .iter()
+            .enumerate()
+            .map(|(o, &other)| other - eta_val * grad[weights_len + biases_len + o])
+            .collect();
+
+        Params::new(new_weights, new_biases, new_others)
     }
 
     /// Overly simple training algorithm for debugging purposes.
@@ -155,13 +386,18 @@ impl Network {
             all_costs: vec![],
             avg_costs: vec![],
             all_grads: vec![],
+            penalties: vec![],
+            clip_counts: vec![],
+            anomalies: vec![],
+            ema_params: None,
+            swa_params: None,
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{autodiff::tape::Tape, prelude::*};
+    use crate::{autodiff::tape::Tape, network::dropout::dropout_seed, prelude::*};
 
     #[test]
     fn test_train() {
@@ -189,6 +425,7 @@ mod tests {
             eta: Eta::point_one(),
             data_set,
             stoch_shuffle_seed: Seed::Input(100.0),
+            ..Default::default()
         };
 
         let train_res = net.train(&settings, params.clone());
@@ -204,6 +441,34 @@ mod tests {
         assert_eq!(train_res.all_grads().len(), 2);
     }
 
+    #[test]
+    fn test_batch_samples() {
+        let net = Network::builder()
+            .input_layer(1)
+            .feed_forward_layer(ActivationFn::Linear, 1)
+            .build();
+        let inputs: Vec<f64> = (0..7).map(|s| s as f64).collect();
+        let settings = TrainingSettings {
+            batch_size: 3,
+            num_epochs: 2,
+            cost_fn: CostFn::MAE,
+            clamp_settings: ClampSettings::NO_CLAMP,
+            eta: Eta::Const(0.0),
+            data_set: DataSet::new(inputs.iter().map(|&x| vec![x]).collect(), vec![vec![0.0]; 7]),
+            stoch_shuffle_seed: Seed::Input(4.0),
+            ..Default::default()
+        };
+        let params = Params::new(vec![1.0], vec![0.0], vec![]);
+
+        // with no learning, the cost of each sample is its input, and every batch starts after the last
+        let res = net.train(&settings, params);
+        for epoch_costs in res.all_costs() {
+            let mut costs: Vec<f64> = epoch_costs.concat();
+            costs.sort_by(f64::total_cmp);
+            assert_eq!(costs, inputs);
+        }
+    }
+
     #[test]
     fn test_get_costs() {
         let layout = Layout::builder()
@@ -233,13 +498,14 @@ mod tests {
             eta: Eta::point_one(),
             data_set,
             stoch_shuffle_seed: Seed::Input(100.0),
+            ..Default::default()
         };
 
         let samples = vec![1, 3, 0, 5, 4, 2];
         let mut tape = Tape::new();
         let vars = params.var_params(&mut tape);
 
-        let costs = net.get_costs(&settings, &samples, &vars, 0, 3);
+        let (costs, _) = net.get_costs(&settings, &samples[0..3], &vars, dropout_seed(settings.stoch_shuffle_seed), 0);
         let costs_not_var = costs.iter().map(|x| x.val()).collect::<Vec<f64>>();
 
         assert_eq!(costs_not_var, [2.0, 4.5, 6.5]);
@@ -264,6 +530,7 @@ mod tests {
             eta: Eta::point_one(),
             data_set: DataSet::default(),
             stoch_shuffle_seed: Seed::Input(100.0),
+            ..Default::default()
         };
 
         let new_params = Network::adjust_params(&grad, &settings, &Eta::point_one(), 0, &params);
@@ -301,6 +568,7 @@ mod tests {
             eta: Eta::point_one(),
             data_set,
             stoch_shuffle_seed: Seed::Input(100.0),
+            ..Default::default()
         };
 
         let samples = vec![1, 3, 0, 5, 4, 2];
@@ -312,7 +580,7 @@ mod tests {
         let mut res = net.run(&vec![1.0, 1.0], &params);
         let cost3 = res.cost(&CostFn::MAE, &vec![0.5, 1E4]);
 
-        let new_params = net.per_batch(&settings, &samples, &params, 0, 0, 3).0;
+        let new_params = net.per_batch(&settings, &samples[..3], &params, dropout_seed(settings.stoch_shuffle_seed), 0, 0).params;
 
         let mut res = net.run(&vec![1.0, 1.0], &new_params);
         let cost1_2 = res.cost(&CostFn::MAE, &vec![0.5, 1E4]);
@@ -325,4 +593,446 @@ mod tests {
         assert!(cost2_2 < cost2);
         assert!(cost3_2 < cost3);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_anomaly_mode() {
+        let net = Network::builder()
+            .input_layer(2)
+            .feed_forward_layer(ActivationFn::ReLU, 2)
+            .feed_forward_layer(ActivationFn::Linear, 2)
+            .build();
+
+        let data_set = DataSet::builder()
+            .sample(vec![0.5, -0.2], vec![0.1, 0.3])
+            .sample(vec![0.1, 0.4], vec![f64::NAN, 0.2])
+            .sample(vec![-0.3, 0.8], vec![0.6, -0.4])
+            .build();
+
+        let mut settings = TrainingSettings {
+            batch_size: 1,
+            num_epochs: 3,
+            cost_fn: CostFn::MSE,
+            clamp_settings: ClampSettings::NO_CLAMP,
+            eta: Eta::point_one(),
+            data_set,
+            stoch_shuffle_seed: Seed::Input(100.0),
+            anomaly_mode: AnomalyMode::SkipBatch,
+            ..Default::default()
+        };
+
+        let train_res = net.train(&settings, net.default_params());
+
+        assert_eq!(train_res.anomalies().len(), 3);
+        for anomaly in train_res.anomalies() {
+            assert_eq!(anomaly.source(), &AnomalySource::Tape { op: "sub".to_string(), partial: false });
+            assert_eq!((anomaly.sample(), anomaly.layer(), anomaly.neuron()), (Some(1), None, None));
+        }
+        assert!(train_res.params().weights().iter().chain(train_res.params().biases()).all(|x| x.is_finite()));
+        assert_eq!(train_res.all_costs().len(), 3);
+
+        settings.anomaly_mode = AnomalyMode::Abort;
+        let train_res = net.train(&settings, net.default_params());
+
+        assert_eq!(train_res.anomalies().len(), 1);
+        assert_eq!(train_res.all_costs().len(), 1);
+        assert_eq!(train_res.all_costs()[0].len(), train_res.anomalies()[0].batch() + 1);
+    }
+
+    #[test]
+    fn test_anomaly_location() {
+        let net = Network::builder()
+            .input_layer(2)
+            .feed_forward_layer(ActivationFn::ReLU, 2)
+            .feed_forward_layer(ActivationFn::Linear, 2)
+            .build();
+
+        let data_set = DataSet::builder()
+            .sample(vec![0.5, -0.2], vec![0.1, 0.3])
+            .sample(vec![0.1, 0.4], vec![0.9, 0.2])
+            .build();
+
+        let mut settings = TrainingSettings {
+            batch_size: 2,
+            num_epochs: 1,
+            cost_fn: CostFn::MSE,
+            clamp_settings: ClampSettings::NO_CLAMP,
+            eta: Eta::point_one(),
+            data_set,
+            stoch_shuffle_seed: Seed::Input(100.0),
+            anomaly_mode: AnomalyMode::Abort,
+            ..Default::default()
+        };
+
+        // a non-finite param is reported where it is stored
+        let mut params = net.default_params();
+        params.weights_mut()[3] = f64::INFINITY;
+        let train_res = net.train(&settings, params.clone());
+        let anomaly = &train_res.anomalies()[0];
+
+        assert_eq!(anomaly.source(), &AnomalySource::Tape { op: "input".to_string(), partial: false });
+        assert_eq!((anomaly.sample(), anomaly.layer(), anomaly.neuron()), (None, Some(1), Some(1)));
+        assert_eq!(train_res.params(), &params);
+
+        // an update that is not finite
+        settings.eta = Eta::Const(f64::NAN);
+        let train_res = net.train(&settings, net.default_params());
+        let anomaly = &train_res.anomalies()[0];
+
+        assert_eq!(anomaly.source(), &AnomalySource::Param);
+        assert_eq!((anomaly.sample(), anomaly.layer(), anomaly.neuron()), (None, Some(1), Some(0)));
+        assert_eq!(train_res.params(), &net.default_params());
+
+        // a forward pass that overflows is rerun to find the layer
+        settings.eta = Eta::point_one();
+        settings.data_set = DataSet::builder()
+            .sample(vec![0.5, -0.2], vec![0.1, 0.3])
+            .sample(vec![f64::MAX, f64::MAX], vec![0.9, 0.2])
+            .build();
+        let train_res = net.train(&settings, net.default_params());
+        let anomaly = &train_res.anomalies()[0];
+
+        assert_eq!(anomaly.source(), &AnomalySource::Tape { op: "matmul".to_string(), partial: false });
+        assert_eq!((anomaly.sample(), anomaly.layer(), anomaly.neuron()), (Some(1), Some(1), Some(0)));
+        assert_eq!(train_res.params(), &net.default_params());
+
+        // the batch is replayed with the masks it was trained with, so an overflow only a kept output causes is found
+        let dropout_net = Network::builder()
+            .input_layer(1)
+            .feed_forward_layer(ActivationFn::Linear, 1)
+            .dropout_layer(0.5)
+            .feed_forward_layer(ActivationFn::Linear, 1)
+            .build();
+        settings.batch_size = 4;
+        settings.data_set = DataSet::builder()
+            .sample(vec![f64::MAX / 1.5], vec![0.0])
+            .sample(vec![f64::MAX / 1.5], vec![0.0])
+            .sample(vec![f64::MAX / 1.5], vec![0.0])
+            .sample(vec![f64::MAX / 1.5], vec![0.0])
+            .build();
+        let params = Params::new(vec![1.0, 1E-300], vec![0.0, 0.0], vec![]);
+        let train_res = dropout_net.train(&settings, params.clone());
+        let anomaly = &train_res.anomalies()[0];
+
+        assert!(anomaly.sample().is_some());
+        assert_eq!((anomaly.layer(), anomaly.neuron()), (Some(2), Some(0)));
+
+        // `Seed::OS` is read once per run, so the replay still has the masks of the pass that overflowed
+        settings.stoch_shuffle_seed = Seed::OS;
+        let train_res = dropout_net.train(&settings, params.clone());
+        for anomaly in train_res.anomalies() {
+            assert_eq!((anomaly.layer(), anomaly.neuron()), (Some(2), Some(0)));
+        }
+        settings.stoch_shuffle_seed = Seed::Input(100.0);
+
+        // others are located like weights and biases
+        let norm_net = Network::builder()
+            .input_layer(2)
+            .feed_forward_layer(ActivationFn::ReLU, 2)
+            .batch_norm_layer()
+            .feed_forward_layer(ActivationFn::Linear, 2)
+            .build();
+        settings.batch_size = 2;
+        settings.data_set = DataSet::builder()
+            .sample(vec![0.5, -0.2], vec![0.1, 0.3])
+            .sample(vec![0.1, 0.4], vec![0.9, 0.2])
+            .build();
+        let mut params = norm_net.random_params(Seed::Input(3.0));
+        params.others_mut()[3] = f64::NAN;
+        let train_res = norm_net.train(&settings, params);
+        let anomaly = &train_res.anomalies()[0];
+
+        assert_eq!(anomaly.source(), &AnomalySource::Tape { op: "input".to_string(), partial: false });
+        assert_eq!((anomaly.sample(), anomaly.layer(), anomaly.neuron()), (None, Some(2), Some(1)));
+    }
+
+    #[test]
+    fn test_parallel_train() {
+        let net = Network::builder()
+            .input_layer(3)
+            .feed_forward_layer(ActivationFn::Sigmoid, 4)
+            .feed_forward_layer(ActivationFn::Linear, 2)
+            .build();
+
+        let data_set = DataSet::builder()
+            .sample(vec![0.5, -0.2, 0.1], vec![0.1, 0.3])
+            .sample(vec![0.1, 0.4, -0.7], vec![-0.5, 0.2])
+            .sample(vec![-0.3, 0.8, 0.2], vec![0.6, -0.4])
+            .sample(vec![0.9, 0.1, 0.4], vec![0.2, 0.7])
+            .sample(vec![-0.6, -0.5, 0.3], vec![-0.8, 0.1])
+            .sample(vec![0.2, 0.2, 0.9], vec![0.4, -0.9])
+            .sample(vec![0.7, -0.9, -0.1], vec![0.0, 0.5])
+            .build();
+
+        let mut settings = TrainingSettings {
+            batch_size: 3,
+            num_epochs: 4,
+            cost_fn: CostFn::MSE,
+            clamp_settings: ClampSettings::NO_CLAMP,
+            eta: Eta::point_one(),
+            data_set,
+            stoch_shuffle_seed: Seed::Input(100.0),
+            ..Default::default()
+        };
+        let params = net.random_params::<f64>(Seed::Input(2.0));
+
+        let serial = net.train(&settings, params.clone());
+        settings.num_threads = 3;
+        let parallel = net.train(&settings, params);
+
+        assert_eq!(parallel.all_costs()[0].iter().map(|x| x.len()).collect::<Vec<usize>>(), [3, 3, 1]);
+        assert_eq!(parallel.all_costs()[0], serial.all_costs()[0]);
+
+        let serial_values = serial.params().weights().iter().chain(serial.params().biases());
+        let parallel_values = parallel.params().weights().iter().chain(parallel.params().biases());
+        for (s, p) in serial_values.zip(parallel_values) {
+            assert!((s - p).abs() < 1E-12);
+        }
+    }
+
+    #[test]
+    fn test_analytic_backend() {
+        let net = Network::builder()
+            .input_layer(3)
+            .feed_forward_layer(ActivationFn::Tanh, 4)
+            .feed_forward_layer(ActivationFn::Linear, 2)
+            .build();
+
+        let data_set = DataSet::builder()
+            .sample(vec![0.5, -0.2, 0.1], vec![0.1, 0.3])
+            .sample(vec![0.1, 0.4, -0.7], vec![-0.5, 0.2])
+            .sample(vec![-0.3, 0.8, 0.2], vec![0.6, -0.4])
+            .sample(vec![0.9, 0.1, 0.4], vec![0.2, 0.7])
+            .build();
+
+        let mut settings = TrainingSettings {
+            batch_size: 2,
+            num_epochs: 5,
+            cost_fn: CostFn::RMSE,
+            clamp_settings: ClampSettings::NO_CLAMP,
+            eta: Eta::point_one(),
+            data_set,
+            stoch_shuffle_seed: Seed::Input(100.0),
+            num_threads: 2,
+            ..Default::default()
+        };
+        let params = net.random_params::<f64>(Seed::Input(4.0));
+
+        let tape = net.train(&settings, params.clone());
+        settings.backend = Backend::Analytic;
+        let analytic = net.train(&settings, params);
+
+        for (t, a) in tape.avg_costs().concat().iter().zip(analytic.avg_costs().concat()) {
+            assert!((t - a).abs() < 1E-12);
+        }
+        for (t, a) in tape.all_grads().concat().concat().iter().zip(analytic.all_grads().concat().concat()) {
+            assert!((t - a).abs() < 1E-12);
+        }
+    }
+
+    #[test]
+    fn test_ema_and_swa() {
+        let net = Network::builder()
+            .input_layer(2)
+            .feed_forward_layer(ActivationFn::Sigmoid, 3)
+            .feed_forward_layer(ActivationFn::Linear, 1)
+            .build();
+
+        let data_set = DataSet::builder()
+            .sample(vec![0.2, -0.5], vec![0.4])
+            .sample(vec![0.8, 0.1], vec![-0.3])
+            .build();
+
+        // one batch per epoch, so the params after each epoch come from training for fewer epochs
+        let settings = |num_epochs| TrainingSettings {
+            batch_size: 2,
+            num_epochs,
+            cost_fn: CostFn::MSE,
+            clamp_settings: ClampSettings::NO_CLAMP,
+            eta: Eta::Const(0.5),
+            data_set: data_set.clone(),
+            stoch_shuffle_seed: Seed::Input(3.0),
+            ema_decay: Some(0.5),
+            swa_start_epoch: Some(1),
+            ..Default::default()
+        };
+        let p0 = net.random_params::<f64>(Seed::Input(6.0));
+        let p: Vec<Params<f64>> = (1..=3).map(|e| net.train(&settings(e), p0.clone()).params().clone()).collect();
+
+        let res = net.train(&settings(3), p0.clone());
+        assert_eq!(res.params(), &p[2]);
+
+        let ema = &(&(&(&p0 + &p[0]) * 0.125) + &(&p[1] * 0.25)) + &(&p[2] * 0.5);
+        assert!((res.ema_params().unwrap() - &ema).norm() < 1E-12);
+
+        let swa = Params::mean_of(&p[1..]).unwrap();
+        assert!((res.swa_params().unwrap() - &swa).norm() < 1E-12);
+
+        // averaging hasn't started by the end of the first epoch
+        let res = net.train(&TrainingSettings { ema_decay: None, ..settings(1) }, p0);
+        assert_eq!(res.ema_params(), None);
+        assert_eq!(res.swa_params(), None);
+    }
+
+    #[test]
+    fn test_regularization() {
+        let net = Network::new(Layout::builder()
+            .input_layer(2)
+            .feed_forward_layer(ActivationFn::Linear, 1)
+            .build());
+        let data_set = DataSet::builder()
+            .sample(vec![0.2, -0.5], vec![0.4])
+            .sample(vec![0.8, 0.1], vec![-0.3])
+            .build();
+        let settings = |regularization| TrainingSettings {
+            batch_size: 2,
+            num_epochs: 20,
+            cost_fn: CostFn::MSE,
+            clamp_settings: ClampSettings::NO_CLAMP,
+            eta: Eta::Const(0.1),
+            data_set: data_set.clone(),
+            stoch_shuffle_seed: Seed::Input(3.0),
+            regularization,
+            ..Default::default()
+        };
+        let p0 = Params::new(vec![2.0, -3.0], vec![1.0], vec![]);
+
+        let plain = net.train(&settings(Regularization::default()), p0.clone());
+        assert!(plain.penalties().iter().flatten().all(|&penalty| penalty == 0.0));
+
+        let l2 = net.train(&settings(Regularization::new().weight_penalty(Penalty::L2(0.5))), p0.clone());
+        assert_eq!(l2.penalties().len(), 20);
+        assert_eq!(l2.penalties()[0][0], 0.5 * (4.0 + 9.0));
+        assert!(l2.penalties()[19][0] < l2.penalties()[0][0]);
+        assert!(l2.params().weights().iter().map(|w| w * w).sum::<f64>() < plain.params().weights().iter().map(|w| w * w).sum::<f64>());
+        // the bias isn't penalized, and the penalty isn't part of the average cost
+        assert_eq!(l2.avg_costs()[0][0], plain.avg_costs()[0][0]);
+
+        let decayed = net.train(&settings(Regularization::new().weight_decay(0.5)), p0.clone());
+        assert!(decayed.penalties().iter().flatten().all(|&penalty| penalty == 0.0));
+        assert!(decayed.params().weights().iter().map(|w| w * w).sum::<f64>() < plain.params().weights().iter().map(|w| w * w).sum::<f64>());
+    }
+
+    #[test]
+    fn test_grad_clip() {
+        let net = Network::new(Layout::builder()
+            .input_layer(2)
+            .feed_forward_layer(ActivationFn::Linear, 1)
+            .build());
+        let settings = |grad_clip| TrainingSettings {
+            batch_size: 1,
+            num_epochs: 3,
+            cost_fn: CostFn::MSE,
+            clamp_settings: ClampSettings::NO_CLAMP,
+            eta: Eta::Const(0.1),
+            data_set: DataSet::builder()
+                .sample(vec![0.2, -0.5], vec![40.0])
+                .sample(vec![0.8, 0.1], vec![-30.0])
+                .build(),
+            stoch_shuffle_seed: Seed::Input(3.0),
+            grad_clip,
+            ..Default::default()
+        };
+        let p0 = Params::new(vec![0.0, 0.0], vec![0.0], vec![]);
+
+        let plain = net.train(&settings(GradClip::default()), p0.clone());
+        assert_eq!(plain.clip_counts(), &vec![0, 0, 0]);
+
+        let clipped = net.train(&settings(GradClip::new().global_norm(1.0)), p0.clone());
+        assert_eq!(clipped.clip_counts(), &vec![2, 2, 2]);
+        // each step moves the params at most eta * 1.0
+        assert!(clipped.params().norm() <= 0.6 + 1E-12);
+        assert!(plain.params().norm() > 0.6);
+    }
+
+    #[test]
+    fn test_dropout() {
+        let net = Network::builder()
+            .input_layer(2)
+            .feed_forward_layer(ActivationFn::Tanh, 8)
+            .dropout_layer(0.5)
+            .feed_forward_layer(ActivationFn::Linear, 1)
+            .build();
+        let settings = |num_threads, backend| TrainingSettings {
+            batch_size: 4,
+            num_epochs: 3,
+            cost_fn: CostFn::MSE,
+            clamp_settings: ClampSettings::NO_CLAMP,
+            eta: Eta::Const(0.1),
+            data_set: DataSet::builder()
+                .sample(vec![0.2, -0.5], vec![0.4])
+                .sample(vec![0.8, 0.1], vec![-0.3])
+                .sample(vec![-0.4, 0.6], vec![0.1])
+                .sample(vec![0.5, 0.5], vec![0.9])
+                .build(),
+            stoch_shuffle_seed: Seed::Input(3.0),
+            num_threads,
+            backend,
+            ..Default::default()
+        };
+        let p0 = net.random_params::<f64>(Seed::Input(2.0));
+
+        let res = net.train(&settings(1, Backend::Tape), p0.clone());
+        // the masks depend on the seed, epoch and sample, but not on the threads or backend
+        assert_eq!(res.params(), net.train(&settings(2, Backend::Tape), p0.clone()).params());
+        assert_eq!(res.params(), net.train(&settings(1, Backend::Analytic), p0.clone()).params());
+        assert_ne!(res.all_costs()[0], res.all_costs()[1]);
+
+        let without_dropout = Network::builder()
+            .input_layer(2)
+            .feed_forward_layer(ActivationFn::Tanh, 8)
+            .feed_forward_layer(ActivationFn::Linear, 1)
+            .build();
+        // the params are the same without the dropout layer, but the costs of the first batch aren't
+        let plain = without_dropout.train(&settings(1, Backend::Tape), p0);
+        assert_ne!(res.all_costs()[0][0], plain.all_costs()[0][0]);
+    }
+
+    #[test]
+    fn test_normalization() {
+        let net = Network::builder()
+            .input_layer(2)
+            .feed_forward_layer(ActivationFn::Tanh, 4)
+            .batch_norm_layer()
+            .feed_forward_layer(ActivationFn::Linear, 3)
+            .layer_norm_layer()
+            .feed_forward_layer(ActivationFn::Linear, 1)
+            .build();
+        let settings = |num_threads, backend| TrainingSettings {
+            batch_size: 2,
+            num_epochs: 5,
+            cost_fn: CostFn::MSE,
+            clamp_settings: ClampSettings::NO_CLAMP,
+            eta: Eta::Const(0.05),
+            data_set: DataSet::builder()
+                .sample(vec![0.2, -0.5], vec![0.4])
+                .sample(vec![0.8, 0.1], vec![-0.3])
+                .sample(vec![-0.4, 0.6], vec![0.1])
+                .sample(vec![0.5, 0.5], vec![0.9])
+                .build(),
+            stoch_shuffle_seed: Seed::Input(3.0),
+            num_threads,
+            backend,
+            ..Default::default()
+        };
+        let p0 = net.random_params::<f64>(Seed::Input(2.0));
+        assert_eq!(p0.others().len(), 22);
+
+        let res = net.train(&settings(1, Backend::Tape), p0.clone());
+        // batch statistics don't depend on how the batch is split, so threads and the analytic backend fall back to one tape
+        assert_eq!(res.params(), net.train(&settings(2, Backend::Tape), p0.clone()).params());
+        assert_eq!(res.params(), net.train(&settings(1, Backend::Analytic), p0.clone()).params());
+
+        // the scales and shifts are trained, and the running statistics move towards those of the batches
+        let others = res.params().others();
+        assert_ne!(others[..8], p0.others()[..8]);
+        assert!(others[8..12].iter().any(|&mean| mean != 0.0));
+        assert!(others[12..16].iter().all(|&variance| variance > 0.0 && variance != 1.0));
+        assert_ne!(others[16..], p0.others()[16..]);
+
+        // running uses the running statistics, so it changes with them
+        let mut others = others.clone();
+        others[8..16].copy_from_slice(&p0.others()[8..16]);
+        let reset = Params::new(res.params().weights().clone(), res.params().biases().clone(), others);
+        assert_ne!(net.run(&vec![0.2, -0.5], res.params()).output(), net.run(&vec![0.2, -0.5], &reset).output());
+    }
+}
@@ -0,0 +1,71 @@
+use bitcode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+/// What the trainer does when a batch produces a non-finite value.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Encode, Decode)]
+pub enum AnomalyMode {
+    /// Nothing is checked.
+    #[default]
+    Off,
+    /// Training stops, keeping the params from before the batch.
+    Abort,
+    /// The update from the batch is discarded and training continues.
+    SkipBatch,
+}
+
+/// Where a non-finite value first appeared in a batch.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Encode, Decode)]
+pub enum AnomalySource {
+    /// A value, or a partial if `partial` is `true`, recorded on the tape by the operation `op`.
+    Tape { op: String, partial: bool },
+    /// The gradient of the cost with respect to a param.
+    Gradient,
+    /// A param after it was updated.
+    Param,
+}
+
+/// A batch in which a non-finite value was found while training in anomaly mode.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Encode, Decode)]
+pub struct Anomaly {
+    pub(crate) epoch: usize,
+    pub(crate) batch: usize,
+    pub(crate) source: AnomalySource,
+    pub(crate) sample: Option<usize>,
+    pub(crate) layer: Option<usize>,
+    pub(crate) neuron: Option<usize>,
+}
+
+impl Anomaly {
+    #[inline]
+    pub fn epoch(&self) -> usize {
+        self.epoch
+    }
+
+    #[inline]
+    pub fn batch(&self) -> usize {
+        self.batch
+    }
+
+    #[inline]
+    pub fn source(&self) -> &AnomalySource {
+        &self.source
+    }
+
+    /// Returns the index in the data set of the sample whose forward pass produced the first non-finite value, if there is one.
+    #[inline]
+    pub fn sample(&self) -> Option<usize> {
+        self.sample
+    }
+
+    /// Returns the index in the `Layout` of the layer in which the non-finite value was found, if it was found in one.
+    #[inline]
+    pub fn layer(&self) -> Option<usize> {
+        self.layer
+    }
+
+    /// Returns the index within `layer` of the neuron at which the non-finite value was found.
+    #[inline]
+    pub fn neuron(&self) -> Option<usize> {
+        self.neuron
+    }
+}
@@ -0,0 +1,144 @@
+use bitcode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+use crate::autodiff::real::Real;
+use crate::network::{Network, network_data::NetworkData, params::Params};
+
+use super::training_settings::TrainingSettings;
+
+/// How the trainer computes gradients.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Encode, Decode)]
+pub enum Backend {
+    /// Every batch is recorded on a `Tape` and differentiated with `Var::backprop`.
+    #[default]
+    Tape,
+    /// Gradients are computed layer by layer without a `Tape`. Falls back to the tape for `CostFn::Custom`,
+    /// for networks with dropout or normalization layers, and when training in anomaly mode.
+    Analytic,
+}
+
+impl Network {
+    /// Computes the costs of `samples` and the gradient of their sum times `scale` with classic layer-wise backpropagation.
+    /// Returns `None` if the cost function has no analytic derivative, or `self` has dropout or normalization layers.
+    #[inline]
+    pub(crate) fn analytic_gradient<'t, T>(&self, settings: &TrainingSettings<'t, T>, samples: &[usize], params: &Params<T>, scale: T) ->
+    Option<(Vec<T>, Vec<T>)>
+    where T: Real, {
+        if self.layout().layers().iter().any(|layer| layer.layer_type().is_dropout() || layer.layer_type().is_norm()) {
+            return None;
+        }
+
+        let net_data = NetworkData::new(self.layout().layers());
+        let num_weights = params.weights().len();
+
+        let mut costs = Vec::with_capacity(samples.len());
+        let mut grad = vec![T::zero(); num_weights + params.biases().len() + params.others().len()];
+        for &sample_idx in samples {
+            let input = settings.data_set.nth_input(sample_idx);
+            let mut sums = Vec::with_capacity(net_data.layer_data.len());
+            let mut activations = vec![input.to_vec()];
+            let res = self.forward_pass_inspect(input, params, |_, layer_sums, layer_activations| {
+                sums.push(layer_sums.to_vec());
+                activations.push(layer_activations.to_vec());
+            });
+
+            let (cost, cost_derivative) = settings.cost_fn().compute_with_derivative(res.output(), &settings.data_set.nth_output(sample_idx).to_vec())?;
+            costs.push(cost);
+
+            // the derivative of the cost with respect to the activations of the current layer
+            let mut delta = cost_derivative;
+            for (l, layer_data) in net_data.layer_data.iter().enumerate().rev() {
+                let activation_fn = layer_data.layer.activation_fn();
+                let inputs = &activations[l];
+
+                let mut input_delta = vec![T::zero(); layer_data.num_inputs];
+                for (n, (&d, &sum)) in delta.iter().zip(&sums[l]).enumerate() {
+                    let sum_delta = d * activation_fn.derivative(sum);
+
+                    let row = layer_data.weight_start_idx + n * layer_data.num_inputs;
+                    for k in 0..layer_data.num_inputs {
+                        grad[row + k] = grad[row + k] + scale * sum_delta * inputs[k];
+                        input_delta[k] = input_delta[k] + params.weights()[row + k] * sum_delta;
+                    }
+
+                    let bias = num_weights + layer_data.bias_start_idx + n;
+                    grad[bias] = grad[bias] + scale * sum_delta;
+                }
+
+                delta = input_delta;
+            }
+        }
+
+        Some((costs, grad))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{autodiff::tape::Tape, network::dropout::dropout_seed, prelude::*};
+
+    #[test]
+    fn analytic_gradient_test() {
+        let activation_fns = [
+            ActivationFn::Linear,
+            ActivationFn::Sigmoid,
+            ActivationFn::Tanh,
+            ActivationFn::ReLU,
+            ActivationFn::GELU,
+            ActivationFn::SiLU,
+            ActivationFn::SmoothReLU,
+        ];
+
+        let data_set = DataSet::builder()
+            .sample(vec![0.5, -0.2, 0.1], vec![0.1, 0.3])
+            .sample(vec![0.1, 0.4, -0.7], vec![-0.5, 0.2])
+            .sample(vec![-0.3, 0.8, 0.2], vec![0.6, -0.4])
+            .build();
+
+        for (i, &activation_fn) in activation_fns.iter().enumerate() {
+            let net = Network::builder()
+                .input_layer(3)
+                .feed_forward_layer(activation_fn, 4)
+                .feed_forward_layer(activation_fns[(i + 1) % activation_fns.len()], 3)
+                .feed_forward_layer(ActivationFn::Linear, 2)
+                .build();
+            let params = net.random_params::<f64>(Seed::Input(i as f64 + 1.0));
+
+            for cost_fn in [CostFn::MSE, CostFn::RMSE, CostFn::MAE] {
+                let settings = TrainingSettings {
+                    cost_fn,
+                    data_set: data_set.clone(),
+                    ..Default::default()
+                };
+
+                let (costs, grad) = net.analytic_gradient(&settings, &[0, 1, 2], &params, 1.0 / 3.0).unwrap();
+
+                let tape = Tape::new();
+                let vars = params.var_params(&tape);
+                let (tape_costs, _) = net.get_costs(&settings, &[0, 1, 2], &vars, dropout_seed(settings.stoch_shuffle_seed), 0);
+                let tape_grad = tape.mean(&tape_costs).backprop();
+
+                assert_eq!(costs, tape_costs.iter().map(|x| x.val()).collect::<Vec<f64>>());
+                for (g, tape_g) in grad.iter().zip(tape_grad.wrt_inputs()) {
+                    assert!((g - tape_g).abs() < 1E-12, "{:?} {:?}: {} != {}", activation_fn, cost_fn, g, tape_g);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn custom_cost_test() {
+        let net = Network::builder()
+            .input_layer(1)
+            .feed_forward_layer(ActivationFn::Linear, 1)
+            .build();
+
+        let settings = TrainingSettings {
+            cost_fn: CostFn::Custom(|output, _| output[0]),
+            data_set: DataSet::builder().sample(vec![1.0], vec![0.0]).build(),
+            ..Default::default()
+        };
+
+        assert_eq!(net.analytic_gradient(&settings, &[0], &net.default_params(), 1.0), None);
+    }
+}
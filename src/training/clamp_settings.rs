@@ -1,4 +1,5 @@
 use crate::autodiff::real::Real;
+use crate::error::Error;
 
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
 pub struct ClampSettings<T: Real> {
@@ -18,15 +19,21 @@ impl<T: Real> ClampSettings<T> {
 
     #[inline]
     pub fn new(weight_min: T, weight_max: T, bias_min: T, bias_max: T) -> Self {
-        if weight_max <= weight_min { panic!("Weight max must be greater than weight min") };
-        if bias_max <= bias_min { panic!("Bias max must be greater than bias min") };
+        Self::try_new(weight_min, weight_max, bias_min, bias_max).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Returns an error instead of panicking if a max is not greater than its min.
+    #[inline]
+    pub fn try_new(weight_min: T, weight_max: T, bias_min: T, bias_max: T) -> Result<Self, Error> {
+        if weight_max <= weight_min { return Err(Error::InvalidSetting("weight max must be greater than weight min".to_string())) };
+        if bias_max <= bias_min { return Err(Error::InvalidSetting("bias max must be greater than bias min".to_string())) };
 
-        ClampSettings {
+        Ok(ClampSettings {
             weight_min,
             weight_max,
             bias_min,
             bias_max,
-        }
+        })
     }
 
     #[inline]
@@ -59,4 +66,10 @@ mod tests {
     fn test_new() {
         ClampSettings::new(1.0, 0.5, 0.0, 1.0);
     }
+
+    #[test]
+    fn test_try_new() {
+        assert!(matches!(ClampSettings::try_new(0.0, 1.0, 0.5, 0.5), Err(Error::InvalidSetting(_))));
+        assert_eq!(ClampSettings::try_new(-1.0, 1.0, -2.0, 2.0).unwrap(), ClampSettings::new(-1.0, 1.0, -2.0, 2.0));
+    }
 }
\ No newline at end of file
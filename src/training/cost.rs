@@ -1,10 +1,12 @@
-use crate::autodiff::real::operations::OperateWithReal;
+use crate::autodiff::real::operations::{OperateWithReal, TensorOperations};
 use crate::autodiff::real::real_math::RealMath;
 use crate::autodiff::real::Real;
+use crate::error::{check_len, Error};
 use crate::network::run_results::RunResults;
+use crate::rng::i64_to_real;
 
 #[derive(Clone, Copy, Debug, Default)]
-pub enum CostFn<T: Real, U: RealMath + OperateWithReal<T>> {
+pub enum CostFn<T: Real, U: RealMath + OperateWithReal<T> + TensorOperations<T>> {
     #[default]
     MSE,
     RMSE,
@@ -12,57 +14,65 @@ pub enum CostFn<T: Real, U: RealMath + OperateWithReal<T>> {
     Custom(fn(&Vec<U>, &Vec<T>) -> U),
 }
 
-impl<T: Real, U: RealMath + OperateWithReal<T>> CostFn<T, U> {
+impl<T: Real, U: RealMath + OperateWithReal<T> + TensorOperations<T>> CostFn<T, U> {
     #[inline]
     pub fn compute(&self, output: &Vec<U>, desired_output: &Vec<T>) -> U {
-        if output.len() != desired_output.len() { panic!("Output and desired output must have same len") };
-        if output.is_empty() { panic!("Len cannot be 0") };
-        match self {
+        self.try_compute(output, desired_output).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Returns an error instead of panicking if `output` is empty or a different len than `desired_output`.
+    #[inline]
+    pub fn try_compute(&self, output: &Vec<U>, desired_output: &Vec<T>) -> Result<U, Error> {
+        check_len("desired output", output.len(), desired_output.len())?;
+        if output.is_empty() { return Err(Error::ShapeMismatch { what: "output", expected: 1, found: 0 }) };
+        Ok(match self {
             CostFn::MSE => CostFn::mse(output, desired_output),
             CostFn::RMSE => CostFn::rmse(output, desired_output),
             CostFn::MAE => CostFn::mae(output, desired_output),
             CostFn::Custom(f) => f(output, desired_output),
-        }
+        })
     }
 
     #[inline]
     pub fn mse(output: &Vec<U>, desired_output: &Vec<T>) -> U {
-        let mut sum = (output[0] - desired_output[0]).powf(T::one() + T::one());
-        let mut len = T::one();
-        for i in 1..output.len() {
-            sum = sum + (output[i] - desired_output[i]).powf(T::one() + T::one());
-            len = len + T::one();
-        }
+        let squares: Vec<U> = (0..output.len()).map(|i| (output[i] - desired_output[i]).powf(T::one() + T::one())).collect();
 
-        sum / len
+        U::mean(&squares)
     }
 
     #[inline]
     pub fn rmse(output: &Vec<U>, desired_output: &Vec<T>) -> U {
-        let mut sum = (output[0] - desired_output[0]).powf(T::one() + T::one());
-        let mut len = T::one();
-        for i in 1..output.len() {
-            sum = sum + (output[i] - desired_output[i]).powf(T::one() + T::one());
-            len = len + T::one();
-        }
-
-        (sum / len).sqrt()
+        Self::mse(output, desired_output).sqrt()
     }
 
     #[inline]
     pub fn mae(output: &Vec<U>, desired_output: &Vec<T>) -> U {
-        let mut sum = (output[0] - desired_output[0]).abs();
-        let mut len = T::one();
-        for i in 1..output.len() {
-            sum = sum + (output[i] - desired_output[i]).abs();
-            len = len + T::one();
-        }
+        let abs_diffs: Vec<U> = (0..output.len()).map(|i| (output[i] - desired_output[i]).abs()).collect();
+
+        U::mean(&abs_diffs)
+    }
 
-        sum / len
+    /// Computes the cost of `output` along with its derivative with respect to each output, 
+    /// using the same partials that the cost would record on a `Tape`. Returns `None` for `Custom` cost functions.
+    #[inline]
+    pub(crate) fn compute_with_derivative(&self, output: &Vec<T>, desired_output: &Vec<T>) -> Option<(T, Vec<T>)> {
+        let two = T::one() + T::one();
+        let len_recip = i64_to_real::<T>(output.len() as i64).recip();
+        let diffs = (0..output.len()).map(|i| output[i] - desired_output[i]);
+        match self {
+            CostFn::MSE => Some((CostFn::mse(output, desired_output), diffs.map(|diff| two * diff.powf(T::one()) * len_recip).collect())),
+            CostFn::RMSE => {
+                let mse = CostFn::<T, T>::mse(output, desired_output);
+                let sqrt_partial = mse.powf(-two.recip()) / two;
+                Some((mse.sqrt(), diffs.map(|diff| sqrt_partial * (two * diff.powf(T::one()) * len_recip)).collect()))
+            },
+            CostFn::MAE => Some((CostFn::mae(output, desired_output), diffs.map(|diff| diff.signum() * len_recip).collect())),
+            CostFn::Custom(_) => None,
+        }
     }
 }
 
-impl<T: Real, U: RealMath + OperateWithReal<T>> RunResults<T, U> {
+impl<T: Real, U: RealMath + OperateWithReal<T> + TensorOperations<T>> RunResults<T, U> {
     #[inline]
     pub fn cost(&mut self, cost_fn: &CostFn<T, U>, desired_output: &Vec<T>) -> U {
         if self.output().len() != desired_output.len() {
@@ -85,5 +95,7 @@ mod tests {
         assert_eq!(CostFn::rmse(&vec![0.5, 0.2, -0.3], &vec![0.4, 0.0, 0.3]), (0.41 / 3f64).sqrt());
         assert_eq!(CostFn::mae(&vec![0.5, 0.2, -0.3], &vec![0.4, 0.0, 0.3]), 0.3);
         assert_eq!(CostFn::MSE.compute(&vec![0.5, 0.2, -0.2], &vec![0.4, 0.0, 0.3]).round_to(4), 0.1);
+        assert!(matches!(CostFn::MAE.try_compute(&vec![0.5, 0.2], &vec![0.4]), Err(Error::ShapeMismatch { expected: 2, found: 1, .. })));
+        assert!(CostFn::<f64, f64>::MSE.try_compute(&vec![], &vec![]).is_err());
     }
 }
\ No newline at end of file
@@ -1,3 +1,5 @@
+use crate::error::{check_len, Error};
+
 #[derive(Clone, Debug, Default, PartialEq, PartialOrd)]
 pub struct DataSet<T: Clone> {
     input_data: Vec<T>,
@@ -17,7 +19,14 @@ impl<T: Clone> DataSet<T> {
 
     #[inline]
     pub fn new(input_data: Vec<Vec<T>>, output_data: Vec<Vec<T>>) -> Self {
-        if input_data.len() != output_data.len() { panic!("Input and output data must have same len") };
+        Self::try_new(input_data, output_data).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Returns an error instead of panicking if there are no samples, or a different number of inputs and outputs.
+    #[inline]
+    pub fn try_new(input_data: Vec<Vec<T>>, output_data: Vec<Vec<T>>) -> Result<Self, Error> {
+        check_len("data set outputs", input_data.len(), output_data.len())?;
+        if input_data.is_empty() { return Err(Error::EmptyDataSet) };
 
         let mut data_set: DataSet<T> = DataSet { 
             input_data: Vec::default(), 
@@ -47,7 +56,7 @@ impl<T: Clone> DataSet<T> {
             data_set.output_data = [data_set.output_data, output_data[s].clone()].concat();
         }
 
-        data_set
+        Ok(data_set)
     }
 
     #[inline]
@@ -1,4 +1,5 @@
 use crate::autodiff::real::Real;
+use crate::error::Error;
 
 use super::i64_to_real;
 
@@ -42,7 +43,13 @@ impl<T: Real> Eta<T> {
     /// Returns the appropriate eta value of `self`.
     #[inline]
     pub fn val(&self, epoch: usize, num_epochs: usize) -> T {
-        if epoch >= num_epochs { panic!("Epoch must be less than number of epochs") };
+        self.try_val(epoch, num_epochs).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Returns an error instead of panicking if `epoch` is not less than `num_epochs`.
+    #[inline]
+    pub fn try_val(&self, epoch: usize, num_epochs: usize) -> Result<T, Error> {
+        if epoch >= num_epochs { return Err(Error::InvalidSetting("epoch must be less than number of epochs".to_string())) };
 
         let inside = self.unwrap();
         let mut val = T::zero();
@@ -59,7 +66,7 @@ impl<T: Real> Eta<T> {
             }
         }
         
-        val
+        Ok(val)
     }
 }
 
@@ -87,5 +94,6 @@ mod tests {
         assert_eq!(Eta::Decay(1.0f64, 0.8123).val(2, 3).round_to(4), 0.8123);
         assert_eq!(Eta::Decay(1.0f64, 0.0001).val(3, 4).round_to(4), 0.0001);
         assert!(Eta::Decay(1.0f64, 0.0001).val(2, 4) > 0.0001 && Eta::Decay(1.0f64, 0.0001).val(2, 4) < 1.0);
+        assert!(matches!(Eta::Const(0.1).try_val(3, 3), Err(Error::InvalidSetting(_))));
     }
 }
\ No newline at end of file
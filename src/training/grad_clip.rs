@@ -0,0 +1,171 @@
+use crate::autodiff::real::Real;
+use crate::error::Error;
+use crate::network::{layout::Layout, network_data::NetworkData};
+
+/// Limits on the gradient of each batch before the params are updated, applied in the order
+/// value, layer norm, then global norm.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct GradClip<T: Real> {
+    pub(super) value: Option<T>,
+    pub(super) layer_norm: Option<T>,
+    pub(super) global_norm: Option<T>,
+}
+
+impl<T: Real> Default for GradClip<T> {
+    #[inline]
+    fn default() -> Self {
+        GradClip {
+            value: None,
+            layer_norm: None,
+            global_norm: None,
+        }
+    }
+}
+
+impl<T: Real> GradClip<T> {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clamps each element of the gradient to `[-max, max]`.
+    #[inline]
+    pub fn value(mut self, max: T) -> Self {
+        self.value = Some(max);
+        self
+    }
+
+    /// Rescales the gradient of each layer's weights and biases, or the scales and shifts of a normalization layer, 
+    /// so its L2 norm is at most `max`.
+    #[inline]
+    pub fn layer_norm(mut self, max: T) -> Self {
+        self.layer_norm = Some(max);
+        self
+    }
+
+    /// Rescales the whole gradient so its L2 norm is at most `max`.
+    #[inline]
+    pub fn global_norm(mut self, max: T) -> Self {
+        self.global_norm = Some(max);
+        self
+    }
+
+    #[inline]
+    pub fn value_max(&self) -> Option<T> {
+        self.value
+    }
+
+    #[inline]
+    pub fn layer_norm_max(&self) -> Option<T> {
+        self.layer_norm
+    }
+
+    #[inline]
+    pub fn global_norm_max(&self) -> Option<T> {
+        self.global_norm
+    }
+
+    /// Returns an error if a limit is not positive.
+    #[inline]
+    pub fn validate(&self) -> Result<(), Error> {
+        for (name, max) in [("value", self.value), ("layer norm", self.layer_norm), ("global norm", self.global_norm)] {
+            if max.is_some_and(|max| max <= T::zero()) {
+                return Err(Error::InvalidSetting(format!("gradient {} clip must be positive", name)));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Clips `grad`, the gradient of the weights then biases then others, and returns whether any limit was hit.
+    #[inline]
+    pub(crate) fn apply(&self, layout: &Layout, grad: &mut [T]) -> bool {
+        let mut clipped = false;
+
+        if let Some(max) = self.value {
+            for g in grad.iter_mut() {
+                if *g > max || *g < -max {
+                    *g = g.clamp(-max, max);
+                    clipped = true;
+                }
+            }
+        }
+
+        if let Some(max) = self.layer_norm {
+            let net_data = NetworkData::new(layout.layers());
+            let num_weights = layout.num_weights();
+            let num_params = num_weights + layout.num_biases();
+            for layer_data in &net_data.layer_data {
+                let biases = layer_data.bias_range();
+                let scale_shifts = layer_data.scale_shift_range();
+                let layer_indices = || layer_data.weight_range()
+                    .chain(biases.start + num_weights..biases.end + num_weights)
+                    .chain(scale_shifts.start + num_params..scale_shifts.end + num_params);
+                let norm = layer_indices().fold(T::zero(), |acc, i| acc + grad[i] * grad[i]).sqrt();
+                if norm > max {
+                    let factor = max / norm;
+                    layer_indices().for_each(|i| grad[i] = grad[i] * factor);
+                    clipped = true;
+                }
+            }
+        }
+
+        if let Some(max) = self.global_norm {
+            let norm = grad.iter().fold(T::zero(), |acc, &g| acc + g * g).sqrt();
+            if norm > max {
+                let factor = max / norm;
+                grad.iter_mut().for_each(|g| *g = *g * factor);
+                clipped = true;
+            }
+        }
+
+        clipped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    #[test]
+    fn grad_clip_test() {
+        let layout = Layout::builder()
+            .input_layer(1)
+            .feed_forward_layer(ActivationFn::Linear, 2)
+            .feed_forward_layer(ActivationFn::Linear, 1)
+            .build();
+        // weights of layer 1, weights of layer 2, biases of layer 1, bias of layer 2
+        let grad = vec![3.0, -4.0, 0.5, 0.5, 0.0, 0.0, 0.5];
+
+        let mut clipped = grad.clone();
+        assert!(GradClip::new().value(1.0).apply(&layout, &mut clipped));
+        assert_eq!(clipped, vec![1.0, -1.0, 0.5, 0.5, 0.0, 0.0, 0.5]);
+
+        let mut clipped = grad.clone();
+        assert!(GradClip::new().layer_norm(2.5).apply(&layout, &mut clipped));
+        assert_eq!(clipped, vec![1.5, -2.0, 0.5, 0.5, 0.0, 0.0, 0.5]);
+
+        let mut clipped = vec![3.0, -4.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+        assert!(GradClip::new().global_norm(2.5).apply(&layout, &mut clipped));
+        assert_eq!(clipped, vec![1.5, -2.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+
+        let mut clipped = grad.clone();
+        assert!(!GradClip::new().value(5.0).global_norm(10.0).apply(&layout, &mut clipped));
+        assert_eq!(clipped, grad);
+
+        // the scales and shifts of a normalization layer are clipped together, but not its running statistics
+        let norm_layout = Layout::builder()
+            .input_layer(1)
+            .feed_forward_layer(ActivationFn::Linear, 2)
+            .batch_norm_layer()
+            .build();
+        // weights, biases, then scales, shifts, running means and running variances
+        let grad = vec![0.0, 0.0, 0.0, 0.0, 3.0, 0.0, 0.0, -4.0, 6.0, 8.0, 0.0, 0.0];
+        let mut clipped = grad.clone();
+        assert!(GradClip::new().layer_norm(2.5).apply(&norm_layout, &mut clipped));
+        assert_eq!(clipped, vec![0.0, 0.0, 0.0, 0.0, 1.5, 0.0, 0.0, -2.0, 6.0, 8.0, 0.0, 0.0]);
+
+        assert!(GradClip::new().layer_norm(0.0).validate().is_err());
+        assert!(GradClip::new().value(1.0).global_norm(2.0).validate().is_ok());
+    }
+}
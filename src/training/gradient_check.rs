@@ -0,0 +1,182 @@
+use crate::autodiff::{gradcheck::{step_size, GradCheck}, real::{operations::TensorOperations, Real}, tape::Tape, var::Var};
+use crate::network::{Network, network_data::NetworkData, params::Params};
+
+use super::cost::CostFn;
+use super::data_set::DataSet;
+
+impl Network {
+    /// Compares the gradient of the average cost over `data_set` found by `backprop` against central finite differences. 
+    /// Returns the maximum relative error of the weights, biases, scales and shifts of each layer after the input layer.
+    /// `cost_fn` gives the cost function for each tape the check records on, such as `|_| CostFn::MSE`.
+    #[inline]
+    pub fn check_gradients<T, C>(&self, params: &Params<T>, data_set: &DataSet<T>, cost_fn: C) -> Vec<T> 
+    where T: Real, C: for<'t> Fn(&'t Tape<T>) -> CostFn<T, Var<'t, T>>, {
+        let check = self.gradient_check(params, data_set, cost_fn);
+        let rel_errors = check.rel_errors();
+        let num_weights = params.weights().len();
+        let num_biases = params.biases().len();
+
+        // the checked scales and shifts follow the biases, layer by layer
+        let mut scale_shift_start = num_weights + num_biases;
+        let net_data = NetworkData::new(self.layout().layers());
+        net_data.layer_data
+            .iter()
+            .map(|layer_data| {
+                let bias_range = layer_data.bias_range();
+                let scale_shift_end = scale_shift_start + layer_data.scale_shift_range().len();
+                let max_error = rel_errors[layer_data.weight_range()]
+                    .iter()
+                    .chain(rel_errors[num_weights + bias_range.start..num_weights + bias_range.end].iter())
+                    .chain(rel_errors[scale_shift_start..scale_shift_end].iter())
+                    .fold(T::zero(), |acc, &x| acc.max(x));
+                scale_shift_start = scale_shift_end;
+
+                max_error
+            })
+            .collect()
+    }
+
+    /// Same as `check_gradients`, but returns the analytic and numeric gradients of every weight, then every bias, 
+    /// then the scales and shifts of each normalization layer. Running statistics aren't trained, so they are left out.
+    #[inline]
+    pub fn gradient_check<T, C>(&self, params: &Params<T>, data_set: &DataSet<T>, cost_fn: C) -> GradCheck<T> 
+    where T: Real, C: for<'t> Fn(&'t Tape<T>) -> CostFn<T, Var<'t, T>>, {
+        // params are indexed as the inputs of the tape: weights, then biases, then others
+        let num_weights = params.weights().len();
+        let num_biases = params.biases().len();
+        let net_data = NetworkData::new(self.layout().layers());
+        let checked: Vec<usize> = (0..num_weights + num_biases)
+            .chain(net_data.layer_data.iter().flat_map(|layer_data| layer_data.scale_shift_range()).map(|i| num_weights + num_biases + i))
+            .collect();
+
+        let tape = Tape::new();
+        let grad = self.data_set_cost(&tape, params, data_set, &cost_fn(&tape)).backprop();
+        let analytic = checked.iter().map(|&i| grad.wrt_inputs()[i]).collect();
+
+        let eval = |params: &Params<T>| {
+            let tape = Tape::new();
+            self.data_set_cost(&tape, params, data_set, &cost_fn(&tape)).val()
+        };
+        // returns `params` with the param at `i` shifted by `delta`
+        let shifted = |i: usize, delta: T| {
+            let mut shifted = params.clone();
+            let param = if i < num_weights {
+                &mut shifted.weights_mut()[i]
+            }
+            else if i < num_weights + num_biases {
+                &mut shifted.biases_mut()[i - num_weights]
+            }
+            else {
+                &mut shifted.others_mut()[i - num_weights - num_biases]
+            };
+            *param = *param + delta;
+            shifted
+        };
+
+        let values: Vec<T> = params.weights().iter().chain(params.biases()).chain(params.others()).copied().collect();
+        let mut numeric = Vec::with_capacity(checked.len());
+        for &i in &checked {
+            let h = step_size(values[i]);
+            numeric.push((eval(&shifted(i, h)) - eval(&shifted(i, -h))) / (h + h));
+        }
+
+        GradCheck {
+            analytic,
+            numeric,
+        }
+    }
+
+    /// Records the average cost of `params` over `data_set` on `tape`.
+    #[inline]
+    fn data_set_cost<'t, T: Real>(&self, tape: &'t Tape<T>, params: &Params<T>, data_set: &DataSet<T>, cost_fn: &CostFn<T, Var<'t, T>>) -> Var<'t, T> {
+        let vars = params.var_params(tape);
+
+        let mut costs = Vec::with_capacity(data_set.len());
+        for s in 0..data_set.len() {
+            let mut res = self.forward_pass(&data_set.nth_input(s).to_vec(), &vars);
+            costs.push(res.cost(cost_fn, &data_set.nth_output(s).to_vec()));
+        }
+
+        Var::mean(&costs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::autodiff::{real::operations::UnaryOperations, tape::Tape, var::Var};
+    use crate::prelude::*;
+
+    fn squared_first_error<'t>(output: &Vec<Var<'t, f64>>, desired_output: &Vec<f64>) -> Var<'t, f64> {
+        (output[0] - desired_output[0]).powi(4).sqrt()
+    }
+
+    #[test]
+    fn test_check_gradients() {
+        let net = Network::builder()
+            .input_layer(3)
+            .feed_forward_layer(ActivationFn::Tanh, 4)
+            .feed_forward_layer(ActivationFn::SiLU, 3)
+            .feed_forward_layer(ActivationFn::Linear, 2)
+            .build();
+
+        let data_set = DataSet::builder()
+            .sample(vec![0.1, -0.4, 0.8], vec![0.5, -0.2])
+            .sample(vec![-0.9, 0.3, 0.2], vec![-1.0, 0.7])
+            .build();
+
+        let params = net.random_params::<f64>(Seed::Input(7.0));
+
+        let cost_fns: [for<'t> fn(&'t Tape<f64>) -> CostFn<f64, Var<'t, f64>>; 3] = [
+            |_| CostFn::MSE, 
+            |_| CostFn::RMSE, 
+            |_| CostFn::Custom(squared_first_error),
+        ];
+        for cost_fn in cost_fns {
+            let errors = net.check_gradients(&params, &data_set, cost_fn);
+
+            assert_eq!(errors.len(), 3);
+            assert!(errors.iter().all(|&x| x < 1E-6), "{:?}", errors);
+        }
+    }
+
+    #[test]
+    fn test_gradient_check() {
+        let net = Network::builder()
+            .input_layer(2)
+            .feed_forward_layer(ActivationFn::Sigmoid, 2)
+            .build();
+
+        let data_set = DataSet::builder()
+            .sample(vec![0.3, -0.6], vec![0.2, 0.9])
+            .build();
+
+        let params = net.random_params::<f64>(Seed::Input(2.0));
+        let check = net.gradient_check(&params, &data_set, |_| CostFn::MSE);
+
+        assert_eq!(check.analytic().len(), 6);
+        assert_eq!(check.numeric().len(), 6);
+
+        // the scales and shifts of normalization layers are checked, but not the running statistics
+        let norm_net = Network::builder()
+            .input_layer(2)
+            .feed_forward_layer(ActivationFn::Tanh, 3)
+            .batch_norm_layer()
+            .feed_forward_layer(ActivationFn::Tanh, 3)
+            .layer_norm_layer()
+            .feed_forward_layer(ActivationFn::Linear, 2)
+            .build();
+        let mut params = norm_net.random_params::<f64>(Seed::Input(5.0));
+        params.others_mut()[0] = 1.5;
+        params.others_mut()[4] = -0.3;
+        params.others_mut()[12] = 0.8;
+        let check = norm_net.gradient_check(&params, &data_set, |_| CostFn::MSE);
+
+        assert_eq!(check.analytic().len(), 21 + 8 + 6 + 6);
+        assert_eq!(check.numeric().len(), check.analytic().len());
+        assert!(check.analytic()[29..].iter().all(|&x| x != 0.0), "{:?}", check.analytic());
+
+        let errors = norm_net.check_gradients(&params, &data_set, |_| CostFn::MSE);
+        assert_eq!(errors.len(), 5);
+        assert!(errors.iter().all(|&x| x < 1E-6), "{:?}", errors);
+    }
+}
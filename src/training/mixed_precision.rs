@@ -0,0 +1,192 @@
+use bitcode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+use crate::autodiff::real::Real;
+use crate::network::{Network, network_data::NetworkData, params::Params};
+
+use super::BatchOutput;
+use super::anomaly::AnomalyMode;
+use super::clamp_settings::ClampSettings;
+use super::grad_clip::GradClip;
+use super::training_results::TrainingResults;
+use super::training_settings::TrainingSettings;
+
+/// How the cost is scaled before backprop in mixed-precision training, so small gradients don't underflow in the compute precision.
+/// The gradient is unscaled in the master precision.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Serialize, Deserialize, Encode, Decode)]
+pub enum LossScale {
+    #[default]
+    None,
+    /// Always scales by the same amount. A batch whose gradient overflows is skipped, 
+    /// so a scale too large for the compute type skips every batch.
+    Static(f64),
+    /// Starts at `init` and halves whenever the gradient overflows, skipping that batch.
+    /// Doubles after `growth_interval` batches in a row without overflow.
+    Dynamic { init: f64, growth_interval: usize },
+}
+
+impl LossScale {
+    #[inline]
+    fn init(&self) -> f64 {
+        match self {
+            LossScale::None => 1.0,
+            LossScale::Static(scale) => *scale,
+            LossScale::Dynamic { init, .. } => *init,
+        }
+    }
+}
+
+impl Network {
+    /// Trains in mixed precision: each batch runs forward and backward with the params rounded to the compute type `C`,
+    /// while the updates accumulate in the `M` master params. `settings` are in the compute type.
+    #[inline]
+    pub fn train_mixed<'t, C, M>(&self, settings: &TrainingSettings<'t, C>, master: Params<M>, loss_scale: LossScale) -> TrainingResults<M>
+    where C: Real, M: Real, {
+        let clamp_settings = ClampSettings {
+            weight_min: convert(settings.weight_min()),
+            weight_max: convert(settings.weight_max()),
+            bias_min: convert(settings.bias_min()),
+            bias_max: convert(settings.bias_max()),
+        };
+
+        let grad_clip = GradClip {
+            value: settings.grad_clip.value.map(convert),
+            layer_norm: settings.grad_clip.layer_norm.map(convert),
+            global_norm: settings.grad_clip.global_norm.map(convert),
+        };
+
+        let mut scale = loss_scale.init();
+        let mut good_batches = 0;
+        self.train_loop(settings, master, |master, batch_samples, seed, e, b| {
+            let params = master.cast::<C>();
+            let b_size = batch_samples.len() as f64;
+
+            let (costs, mut grad, tape_anomaly, batch_stats) = self.batch_gradient(settings, batch_samples, &params, C::from_f64(scale / b_size), seed, e);
+            let penalty = convert(settings.regularization.apply(self.layout(), &params, &mut grad, C::from_f64(scale)));
+            let master_costs: Vec<M> = convert_all(&costs);
+            let avg_cost = master_costs.iter().fold(M::zero(), |acc, &cost| acc + cost) / M::from_f64(b_size);
+            let mut master_grad: Vec<M> = grad.iter().map(|g| M::from_f64(g.to_f64() / scale)).collect();
+
+            // an overflow means the scale is too large rather than an anomaly, so the batch is skipped
+            if loss_scale != LossScale::None && !grad.iter().all(|g| g.is_finite()) {
+                if let LossScale::Dynamic { .. } = loss_scale {
+                    scale /= 2.0;
+                    good_batches = 0;
+                }
+                return BatchOutput { params: master.clone(), costs: master_costs, avg_cost, grad: master_grad, penalty, clipped: false, anomaly: None };
+            }
+
+            if let LossScale::Dynamic { growth_interval, .. } = loss_scale {
+                good_batches += 1;
+                if good_batches == growth_interval {
+                    scale *= 2.0;
+                    good_batches = 0;
+                }
+            }
+
+            // the unscaled gradient is clipped in the master precision
+            let clipped = grad_clip.apply(self.layout(), &mut master_grad);
+            let eta_val = M::from_f64(settings.eta().val(e, settings.num_epochs).to_f64());
+            let weight_decay = convert(settings.regularization.weight_decay);
+            let mut new_master = Self::update_params(&master_grad, &clamp_settings, eta_val, weight_decay, master);
+            batch_stats.update_running(&NetworkData::new(self.layout().layers()), &mut new_master);
+
+            let anomaly = match settings.anomaly_mode != AnomalyMode::Off {
+                true => self.find_anomaly(settings, batch_samples, &params, (tape_anomaly, &costs, &grad, &new_master), (seed, e, b)),
+                false => None,
+            };
+
+            BatchOutput { params: new_master, costs: master_costs, avg_cost, grad: master_grad, penalty, clipped, anomaly }
+        })
+    }
+}
+
+#[inline]
+fn convert<U: Real, V: Real>(x: U) -> V {
+    V::from_f64(x.to_f64())
+}
+
+#[inline]
+fn convert_all<U: Real, V: Real>(xs: &[U]) -> Vec<V> {
+    xs.iter().map(|&x| convert(x)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    fn net_and_settings() -> (Network, TrainingSettings<'static, f32>) {
+        let net = Network::builder()
+            .input_layer(2)
+            .feed_forward_layer(ActivationFn::Tanh, 3)
+            .feed_forward_layer(ActivationFn::Linear, 1)
+            .build();
+
+        let settings = TrainingSettings {
+            batch_size: 2,
+            num_epochs: 20,
+            clamp_settings: ClampSettings::NO_CLAMP,
+            eta: Eta::point_one(),
+            stoch_shuffle_seed: Seed::Input(7.0),
+            data_set: DataSet::builder()
+                .sample(vec![0.1, 0.5], vec![0.3])
+                .sample(vec![-0.4, 0.2], vec![-0.1])
+                .sample(vec![0.7, -0.3], vec![0.5])
+                .sample(vec![0.0, 0.9], vec![0.2])
+                .build(),
+            ..Default::default()
+        };
+
+        (net, settings)
+    }
+
+    #[test]
+    fn train_mixed_test() {
+        let (net, settings) = net_and_settings();
+        let master = net.random_params::<f64>(Seed::Input(3.0));
+
+        let res = net.train_mixed(&settings, master.clone(), LossScale::None);
+        let epoch_costs = res.epoch_cost(6);
+        assert!(epoch_costs.last().unwrap() < epoch_costs.first().unwrap());
+        assert_eq!(res.params().weights().len(), master.weights().len());
+
+        // a power of two loss scale is undone exactly
+        let scaled = net.train_mixed(&settings, master.clone(), LossScale::Static(1024.0));
+        assert_eq!(scaled.avg_costs(), res.avg_costs());
+        for (w, scaled_w) in res.params().weights().iter().zip(scaled.params().weights()) {
+            assert!((w - scaled_w).abs() < 1E-6);
+        }
+
+        // updates too small for f32 still accumulate in the f64 master weights
+        let tiny_eta = TrainingSettings { eta: Eta::Const(1E-9), num_epochs: 1, ..settings.clone() };
+        let res = net.train_mixed(&tiny_eta, master.clone(), LossScale::None);
+        let f32_res = net.train(&tiny_eta, Params::new(
+            master.weights().iter().map(|&w| w as f32).collect(),
+            master.biases().iter().map(|&b| b as f32).collect(),
+            vec![],
+        ));
+        assert_ne!(res.params(), &master);
+        assert!(f32_res.params().weights().iter().zip(master.weights()).all(|(&w, &m)| w == m as f32));
+    }
+
+    #[test]
+    fn dynamic_loss_scale_test() {
+        let (net, settings) = net_and_settings();
+        let master = net.random_params::<f64>(Seed::Input(3.0));
+
+        // the first batches overflow in f32 and are skipped until the scale comes down
+        let res = net.train_mixed(&settings, master.clone(), LossScale::Dynamic { init: 2f64.powi(130), growth_interval: 1000 });
+        let grads = res.all_grads();
+        assert!(!grads[0][0].iter().all(|g| g.is_finite()));
+        assert!(grads.last().unwrap().iter().flatten().all(|g| g.is_finite()));
+        assert!(res.anomalies().is_empty());
+
+        let unscaled = net.train_mixed(&settings, master.clone(), LossScale::None);
+        assert!((res.epoch_cost(6).last().unwrap() - unscaled.epoch_cost(6).last().unwrap()).abs() < 1E-3);
+
+        // a static scale that overflows skips every batch instead of breaking the params
+        let res = net.train_mixed(&settings, master.clone(), LossScale::Static(2f64.powi(130)));
+        assert_eq!(res.params(), &master);
+        assert!(res.anomalies().is_empty());
+    }
+}
@@ -0,0 +1,246 @@
+use crate::autodiff::real::Real;
+use crate::error::Error;
+use crate::network::{layer::LayerType, layout::Layout, network_data::NetworkData, params::Params};
+
+/// A penalty on the size of each param.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub enum Penalty<T: Real> {
+    #[default]
+    None,
+    /// `c * |x|`
+    L1(T),
+    /// `c * x^2`
+    L2(T),
+    /// `l1 * |x| + l2 * x^2`
+    ElasticNet(T, T),
+}
+
+impl<T: Real> Penalty<T> {
+    #[inline]
+    pub fn compute(&self, x: T) -> T {
+        match *self {
+            Penalty::None => T::zero(),
+            Penalty::L1(c) => c * x.abs(),
+            Penalty::L2(c) => c * x * x,
+            Penalty::ElasticNet(l1, l2) => l1 * x.abs() + l2 * x * x,
+        }
+    }
+
+    /// Returns the coefficients of the penalty.
+    #[inline]
+    fn coefficients(&self) -> Vec<T> {
+        match *self {
+            Penalty::None => vec![],
+            Penalty::L1(c) | Penalty::L2(c) => vec![c],
+            Penalty::ElasticNet(l1, l2) => vec![l1, l2],
+        }
+    }
+
+    /// The derivative of the penalty, taking the derivative of `|x|` at 0 to be 0.
+    #[inline]
+    pub fn derivative(&self, x: T) -> T {
+        let sign = if x == T::zero() { T::zero() } else { x.signum() };
+        let two = T::one() + T::one();
+        match *self {
+            Penalty::None => T::zero(),
+            Penalty::L1(c) => c * sign,
+            Penalty::L2(c) => two * c * x,
+            Penalty::ElasticNet(l1, l2) => l1 * sign + two * l2 * x,
+        }
+    }
+}
+
+/// Penalties on the size of the weights and biases, added to the average cost of each batch,
+/// and weight decay, which shrinks the weights in the update without being part of the cost.
+/// The penalties and their gradient are computed analytically rather than on the tape, 
+/// so anomaly mode only sees them in the gradient, and `Network::gradient_check` leaves them out.
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+pub struct Regularization<T: Real> {
+    pub(super) weight_penalty: Penalty<T>,
+    pub(super) bias_penalty: Penalty<T>,
+    pub(super) layer_penalties: Vec<(usize, Penalty<T>, Penalty<T>)>,
+    pub(super) weight_decay: T,
+}
+
+impl<T: Real> Default for Regularization<T> {
+    #[inline]
+    fn default() -> Self {
+        Regularization {
+            weight_penalty: Penalty::None,
+            bias_penalty: Penalty::None,
+            layer_penalties: Vec::default(),
+            weight_decay: T::zero(),
+        }
+    }
+}
+
+impl<T: Real> Regularization<T> {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    pub fn weight_penalty(mut self, penalty: Penalty<T>) -> Self {
+        self.weight_penalty = penalty;
+        self
+    }
+
+    #[inline]
+    pub fn bias_penalty(mut self, penalty: Penalty<T>) -> Self {
+        self.bias_penalty = penalty;
+        self
+    }
+
+    /// Overrides the weight and bias penalties of layer `l` of the layout.
+    #[inline]
+    pub fn layer_penalty(mut self, l: usize, weight_penalty: Penalty<T>, bias_penalty: Penalty<T>) -> Self {
+        self.layer_penalties.retain(|&(layer, _, _)| layer != l);
+        self.layer_penalties.push((l, weight_penalty, bias_penalty));
+        self
+    }
+
+    /// Each update subtracts `eta * decay * w` from each weight `w`.
+    #[inline]
+    pub fn weight_decay(mut self, decay: T) -> Self {
+        self.weight_decay = decay;
+        self
+    }
+
+    /// Returns the weight and bias penalties of layer `l` of the layout.
+    #[inline]
+    pub fn penalties(&self, l: usize) -> (Penalty<T>, Penalty<T>) {
+        self.layer_penalties
+            .iter()
+            .find(|&&(layer, _, _)| layer == l)
+            .map(|&(_, weight_penalty, bias_penalty)| (weight_penalty, bias_penalty))
+            .unwrap_or((self.weight_penalty, self.bias_penalty))
+    }
+
+    /// Returns an error if a penalty coefficient or the weight decay is negative or not finite,
+    /// or a layer penalty is not on a feed-forward layer of `layout`.
+    #[inline]
+    pub fn validate(&self, layout: &Layout) -> Result<(), Error> {
+        let penalties = [self.weight_penalty, self.bias_penalty]
+            .into_iter()
+            .chain(self.layer_penalties.iter().flat_map(|&(_, w, b)| [w, b]));
+        let coefficients = penalties.flat_map(|penalty| penalty.coefficients()).chain([self.weight_decay]);
+        for c in coefficients {
+            if !c.is_finite() || c < T::zero() {
+                return Err(Error::InvalidSetting("regularization coefficients must be finite and non-negative".to_string()));
+            }
+        }
+
+        let layers = layout.layers();
+        for &(l, _, _) in &self.layer_penalties {
+            if l >= layers.len() {
+                return Err(Error::OutOfBounds { what: "layer", idx: l, len: layers.len() });
+            }
+            if layers[l].layer_type() != LayerType::FeedForward {
+                return Err(Error::InvalidSetting(format!("layer {} has no weights or biases to penalize", l)));
+            }
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    fn has_penalty(&self) -> bool {
+        self.weight_penalty != Penalty::None
+            || self.bias_penalty != Penalty::None
+            || self.layer_penalties.iter().any(|&(_, w, b)| w != Penalty::None || b != Penalty::None)
+    }
+
+    /// Returns the total penalty of `params` and adds its analytic gradient times `scale` to `grad`.
+    #[inline]
+    pub(crate) fn apply(&self, layout: &Layout, params: &Params<T>, grad: &mut [T], scale: T) -> T {
+        if !self.has_penalty() {
+            return T::zero();
+        }
+
+        let net_data = NetworkData::new(layout.layers());
+        let num_weights = params.weights().len();
+        let mut penalty = T::zero();
+        for (l, layer_data) in net_data.layer_data.iter().enumerate() {
+            // layer data starts after the input layer
+            let (weight_penalty, bias_penalty) = self.penalties(l + 1);
+
+            for w in layer_data.weight_range() {
+                let x = params.weights()[w];
+                penalty = penalty + weight_penalty.compute(x);
+                grad[w] = grad[w] + scale * weight_penalty.derivative(x);
+            }
+            for b in layer_data.bias_range() {
+                let x = params.biases()[b];
+                penalty = penalty + bias_penalty.compute(x);
+                grad[num_weights + b] = grad[num_weights + b] + scale * bias_penalty.derivative(x);
+            }
+        }
+
+        penalty
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{autodiff::tape::Tape, prelude::*};
+    use crate::autodiff::real::operations::UnaryOperations;
+
+    #[test]
+    fn penalty_test() {
+        let layout = Layout::builder()
+            .input_layer(2)
+            .feed_forward_layer(ActivationFn::Tanh, 2)
+            .feed_forward_layer(ActivationFn::Linear, 1)
+            .build();
+        let params = Params::new(vec![0.5, -1.0, 0.0, 2.0, -0.5, 0.25], vec![0.1, -0.2, 0.3], vec![]);
+
+        let regularization = Regularization::new()
+            .weight_penalty(Penalty::ElasticNet(0.1, 0.01))
+            .bias_penalty(Penalty::L2(0.5))
+            .layer_penalty(2, Penalty::L1(0.3), Penalty::None);
+
+        let mut grad = vec![0.0; 9];
+        let penalty = regularization.apply(&layout, &params, &mut grad, 2.0);
+
+        // the same penalty recorded on a tape
+        let tape = Tape::new();
+        let vars = params.var_params(&tape);
+        let mut terms = Vec::default();
+        for w in &vars.weights()[..4] {
+            terms.push(w.abs() * 0.1 + w.powi(2) * 0.01);
+        }
+        for w in &vars.weights()[4..] {
+            terms.push(w.abs() * 0.3);
+        }
+        for b in &vars.biases()[..2] {
+            terms.push(b.powi(2) * 0.5);
+        }
+        let tape_penalty = tape.sum(&terms);
+        let tape_grad = tape_penalty.backprop();
+
+        assert!((penalty - tape_penalty.val()).abs() < 1E-12);
+        for (i, (g, tape_g)) in grad.iter().zip(tape_grad.wrt_inputs()).enumerate() {
+            // the tape has its own derivative of |x| at 0
+            if i != 2 {
+                assert!((g - 2.0 * tape_g).abs() < 1E-12, "{}: {} != {}", i, g, tape_g);
+            }
+        }
+        assert_eq!(grad[2], 0.0);
+        assert_eq!(grad[8], 0.0);
+
+        let mut grad = vec![0.0; 9];
+        assert_eq!(Regularization::new().weight_decay(0.1).apply(&layout, &params, &mut grad, 1.0), 0.0);
+        assert_eq!(grad, vec![0.0; 9]);
+
+        assert!(regularization.validate(&layout).is_ok());
+        assert!(matches!(Regularization::new().weight_penalty(Penalty::L2(-0.1)).validate(&layout), Err(Error::InvalidSetting(_))));
+        assert!(matches!(Regularization::new().bias_penalty(Penalty::ElasticNet(0.1, f64::NAN)).validate(&layout), Err(Error::InvalidSetting(_))));
+        assert!(matches!(Regularization::new().weight_decay(-0.1).validate(&layout), Err(Error::InvalidSetting(_))));
+        assert!(matches!(Regularization::new().layer_penalty(0, Penalty::L1(0.1), Penalty::None).validate(&layout), Err(Error::InvalidSetting(_))));
+        assert!(matches!(
+            Regularization::new().layer_penalty(3, Penalty::L1(0.1), Penalty::None).validate(&layout),
+            Err(Error::OutOfBounds { what: "layer", idx: 3, len: 3 }),
+        ));
+    }
+}
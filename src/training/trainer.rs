@@ -1,10 +1,15 @@
 use crate::autodiff::{real::Real, var::Var};
+use crate::error::{check_len, Error};
 use crate::network::{Network, params::Params};
 use crate::rng::Seed;
 use crate::training::{clamp_settings::ClampSettings, data_set::DataSet, training_settings::TrainingSettings};
 
+use super::anomaly::AnomalyMode;
+use super::backprop::Backend;
 use super::cost::CostFn;
 use super::eta::Eta;
+use super::grad_clip::GradClip;
+use super::regularization::Regularization;
 use super::training_results::TrainingResults;
 
 #[derive(Clone, Debug)]
@@ -18,6 +23,13 @@ pub struct NetworkTrainer<'t, T: Real> {
     eta: Eta<T>,
     data_set: Option<DataSet<T>>,
     stoch_shuffle_seed: Seed<T>,
+    anomaly_mode: AnomalyMode,
+    num_threads: usize,
+    backend: Backend,
+    ema_decay: Option<T>,
+    swa_start_epoch: Option<usize>,
+    regularization: Regularization<T>,
+    grad_clip: GradClip<T>,
 }
 
 impl<'t, T: Real> NetworkTrainer<'t, T> {
@@ -33,6 +45,13 @@ impl<'t, T: Real> NetworkTrainer<'t, T> {
             eta: Eta::point_one(),
             data_set: None,
             stoch_shuffle_seed: Seed::OS,
+            anomaly_mode: AnomalyMode::Off,
+            num_threads: 1,
+            backend: Backend::Tape,
+            ema_decay: None,
+            swa_start_epoch: None,
+            regularization: Regularization::default(),
+            grad_clip: GradClip::default(),
         }
     }
 
@@ -50,6 +69,14 @@ impl<'t, T: Real> NetworkTrainer<'t, T> {
         self.clamp_settings = settings.clamp_settings;
         self.eta = settings.eta;
         self.data_set = Some(settings.data_set);
+        self.stoch_shuffle_seed = settings.stoch_shuffle_seed;
+        self.anomaly_mode = settings.anomaly_mode;
+        self.num_threads = settings.num_threads;
+        self.backend = settings.backend;
+        self.ema_decay = settings.ema_decay;
+        self.swa_start_epoch = settings.swa_start_epoch;
+        self.regularization = settings.regularization;
+        self.grad_clip = settings.grad_clip;
         self
     }
 
@@ -119,18 +146,89 @@ impl<'t, T: Real> NetworkTrainer<'t, T> {
         self
     }
 
+    /// Checks every batch for non-finite values, recording where they appeared in the `TrainingResults`.
+    #[inline]
+    pub fn anomaly_mode(mut self, mode: AnomalyMode) -> Self {
+        self.anomaly_mode = mode;
+        self
+    }
+
+    /// Splits each batch across `n` threads, each computing the gradient of its share of the batch on its own tape.
+    #[inline]
+    pub fn num_threads(mut self, n: usize) -> Self {
+        self.num_threads = n;
+        self
+    }
+
+    #[inline]
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Keeps an exponential moving average of the params, returned in `TrainingResults::ema_params`.
+    /// `decay` must be in `[0, 1)`.
+    #[inline]
+    pub fn ema_decay(mut self, decay: T) -> Self {
+        self.ema_decay = Some(decay);
+        self
+    }
+
+    /// Averages the params at the end of each epoch from `epoch` on, returned in `TrainingResults::swa_params`.
+    #[inline]
+    pub fn swa_start_epoch(mut self, epoch: usize) -> Self {
+        self.swa_start_epoch = Some(epoch);
+        self
+    }
+
+    #[inline]
+    pub fn regularization(mut self, regularization: Regularization<T>) -> Self {
+        self.regularization = regularization;
+        self
+    }
+
+    /// Clips the gradient of each batch before the update, counted in `TrainingResults::clip_counts`.
+    #[inline]
+    pub fn grad_clip(mut self, grad_clip: GradClip<T>) -> Self {
+        self.grad_clip = grad_clip;
+        self
+    }
+
     #[inline]
     pub fn train(self) -> TrainingResults<T> {
-        if self.params.is_none() { panic!("Params must be explicitly set") };
-        if self.batch_size.is_none() { panic!("Batch size must be explicitly set") };
-        if self.num_epochs.is_none() { panic!("Num epochs must be explicitly set") };
-        if self.data_set.is_none() { panic!("Data set must be explicitly set") };
-        if self.batch_size.unwrap() > self.data_set.clone().unwrap().len() { panic!("Batch size cannot be larger than data set") }
-        if self.network.layout().layers()[0].num_neurons() != self.data_set.clone().unwrap().nth_input(0).len() { 
-            panic!("Network input len must match that of data set");
+        self.try_train().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Returns an error instead of panicking if a setting is missing or invalid, 
+    /// or the params or data set don't fit the network.
+    #[inline]
+    pub fn try_train(self) -> Result<TrainingResults<T>, Error> {
+        let missing = |name: &str| Error::InvalidSetting(format!("{} must be explicitly set", name));
+        let params = self.params.as_ref().ok_or_else(|| missing("params"))?;
+        let batch_size = self.batch_size.ok_or_else(|| missing("batch size"))?;
+        self.num_epochs.ok_or_else(|| missing("num epochs"))?;
+        let data_set = self.data_set.as_ref().ok_or_else(|| missing("data set"))?;
+
+        if data_set.len() == 0 { return Err(Error::EmptyDataSet) };
+        if batch_size == 0 || batch_size > data_set.len() { 
+            return Err(Error::InvalidSetting("batch size must be greater than 0 and no larger than data set".to_string()));
+        }
+        // the variance of a single sample is zero, so batch normalization would only see its shift
+        if self.network.has_batch_norm() && (batch_size == 1 || data_set.len() % batch_size == 1) {
+            return Err(Error::InvalidSetting("batch normalization needs at least 2 samples in every batch".to_string()));
         }
-        if self.network.layout().layers().last().unwrap().num_neurons() != self.data_set.clone().unwrap().nth_output(0).len() { 
-            panic!("Network output len must match that of data set");
+        self.stoch_shuffle_seed.validate()?;
+        self.grad_clip.validate()?;
+        self.regularization.validate(self.network.layout())?;
+        if self.ema_decay.is_some_and(|decay| !(decay >= T::zero() && decay < T::one())) {
+            return Err(Error::InvalidSetting("EMA decay must be at least 0 and less than 1".to_string()));
+        }
+
+        self.network.check_params(params)?;
+        let layers = self.network.layout().layers();
+        for s in 0..data_set.len() {
+            check_len("data set input", layers[0].num_neurons(), data_set.nth_input(s).len())?;
+            check_len("data set output", layers.last().unwrap().num_neurons(), data_set.nth_output(s).len())?;
         }
 
         let settings = TrainingSettings {
@@ -141,9 +239,16 @@ impl<'t, T: Real> NetworkTrainer<'t, T> {
             eta: self.eta,
             data_set: self.data_set.unwrap(),
             stoch_shuffle_seed: self.stoch_shuffle_seed,
+            anomaly_mode: self.anomaly_mode,
+            num_threads: self.num_threads,
+            backend: self.backend,
+            ema_decay: self.ema_decay,
+            swa_start_epoch: self.swa_start_epoch,
+            regularization: self.regularization,
+            grad_clip: self.grad_clip,
         };
 
-        self.network.train::<T>(&settings, self.params.unwrap())
+        Ok(self.network.train::<T>(&settings, self.params.unwrap()))
     }
 }
 
@@ -189,4 +294,98 @@ mod tests {
         println!("res2: {:?}", res);
         println!("new params: {:?}", optimized);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn try_train_test() {
+        let net = Network::builder()
+            .input_layer(2)
+            .feed_forward_layer(ActivationFn::Linear, 1)
+            .build();
+        let data_set = DataSet::builder()
+            .sample(vec![0.1, 0.2], vec![0.3])
+            .sample(vec![0.4, 0.5], vec![0.6])
+            .build();
+
+        let trainer = net.trainer()
+            .data_set(data_set.clone())
+            .params(net.default_params())
+            .batch_size(2)
+            .num_epochs(2);
+
+        assert!(trainer.clone().try_train().is_ok());
+        assert!(matches!(net.trainer::<f64>().try_train(), Err(Error::InvalidSetting(_))));
+        assert!(matches!(trainer.clone().batch_size(3).try_train(), Err(Error::InvalidSetting(_))));
+        assert!(matches!(trainer.clone().stoch_shuffle_seed(Seed::Input(-1.0)).try_train(), Err(Error::InvalidSeed)));
+        assert!(matches!(trainer.clone().regularization(Regularization::new().weight_decay(-1.0)).try_train(), Err(Error::InvalidSetting(_))));
+        assert!(trainer.clone().ema_decay(0.0).try_train().is_ok());
+        for decay in [-0.1, 1.0, f64::NAN] {
+            assert!(matches!(trainer.clone().ema_decay(decay).try_train(), Err(Error::InvalidSetting(_))));
+        }
+        assert!(matches!(trainer.clone().params(Params::new(vec![1.0], vec![1.0], vec![])).try_train(), Err(Error::ShapeMismatch { what: "weights", .. })));
+        assert!(matches!(
+            trainer.clone().data_set(DataSet::builder().sample(vec![0.1, 0.2], vec![0.3]).sample(vec![0.4], vec![0.6]).build()).batch_size(1).try_train(), 
+            Err(Error::ShapeMismatch { what: "data set input", expected: 2, found: 1 }),
+        ));
+        assert!(matches!(DataSet::<f64>::try_new(vec![], vec![]), Err(Error::EmptyDataSet)));
+
+        let norm_net = Network::builder()
+            .input_layer(2)
+            .feed_forward_layer(ActivationFn::ReLU, 2)
+            .layer_norm_layer()
+            .feed_forward_layer(ActivationFn::Linear, 1)
+            .build();
+        let norm_params = norm_net.default_params();
+        let without_others = Params::new(norm_params.weights().clone(), norm_params.biases().clone(), vec![]);
+        assert!(matches!(
+            norm_net.trainer().data_set(data_set.clone()).params(without_others).batch_size(2).num_epochs(2).try_train(),
+            Err(Error::ShapeMismatch { what: "others", expected: 4, found: 0 }),
+        ));
+
+        let batch_norm_net = Network::builder()
+            .input_layer(2)
+            .feed_forward_layer(ActivationFn::ReLU, 2)
+            .batch_norm_layer()
+            .feed_forward_layer(ActivationFn::Linear, 1)
+            .build();
+        let three_samples = DataSet::builder()
+            .sample(vec![0.1, 0.2], vec![0.3])
+            .sample(vec![0.4, 0.5], vec![0.6])
+            .sample(vec![0.7, 0.8], vec![0.9])
+            .build();
+        let batch_norm_trainer = batch_norm_net.trainer().data_set(three_samples).params(batch_norm_net.default_params()).num_epochs(1);
+        assert!(batch_norm_trainer.clone().batch_size(3).try_train().is_ok());
+        assert!(matches!(batch_norm_trainer.clone().batch_size(1).try_train(), Err(Error::InvalidSetting(_))));
+        // the last batch would hold a single sample
+        assert!(matches!(batch_norm_trainer.clone().batch_size(2).try_train(), Err(Error::InvalidSetting(_))));
+        assert!(matches!(DataSet::try_new(vec![vec![0.1]], vec![]), Err(Error::ShapeMismatch { .. })));
+    }
+
+    #[test]
+    fn training_settings_test() {
+        let net = Network::builder()
+            .input_layer(2)
+            .feed_forward_layer(ActivationFn::Tanh, 3)
+            .feed_forward_layer(ActivationFn::Linear, 1)
+            .build();
+        let settings = TrainingSettings {
+            batch_size: 1,
+            num_epochs: 3,
+            cost_fn: CostFn::MSE,
+            clamp_settings: ClampSettings::NO_CLAMP,
+            eta: Eta::Const(0.1),
+            data_set: DataSet::builder()
+                .sample(vec![0.2, -0.5], vec![0.4])
+                .sample(vec![0.8, 0.1], vec![-0.3])
+                .sample(vec![-0.4, 0.6], vec![0.1])
+                .sample(vec![0.5, 0.5], vec![0.9])
+                .build(),
+            stoch_shuffle_seed: Seed::Input(3.0),
+            ..Default::default()
+        };
+        let params = net.random_params::<f64>(Seed::Input(2.0));
+
+        // the shuffle seed of the settings is kept, so the samples are shuffled the same way
+        let res = net.trainer().training_settings(settings.clone()).params(params.clone()).train();
+        assert_eq!(res.params(), net.train(&settings, params).params());
+    }
+}
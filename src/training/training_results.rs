@@ -7,6 +7,7 @@ use serde::{Deserialize, Serialize};
 use crate::autodiff::real::Real;
 use crate::network::{layout::Layout, params::Params};
 use crate::save_information::{FileNotation, SaveInformation};
+use super::anomaly::Anomaly;
 use super::i64_to_real;
 
 /// The data returned after training a `Network`.
@@ -17,6 +18,16 @@ pub struct TrainingResults<T: Real> {
     pub(super) all_costs: Vec<Vec<Vec<T>>>,
     pub(super) avg_costs: Vec<Vec<T>>,
     pub(super) all_grads: Vec<Vec<Vec<T>>>,
+    #[serde(default)]
+    pub(super) penalties: Vec<Vec<T>>,
+    #[serde(default)]
+    pub(super) clip_counts: Vec<usize>,
+    #[serde(default)]
+    pub(super) anomalies: Vec<Anomaly>,
+    #[serde(default)]
+    pub(super) ema_params: Option<Params<T>>,
+    #[serde(default)]
+    pub(super) swa_params: Option<Params<T>>,
 }
 
 impl<T: Real> TrainingResults<T> {
@@ -50,6 +61,37 @@ impl<T: Real> TrainingResults<T> {
         &self.all_grads
     }       
 
+    /// Returns the regularization penalty of the params before each batch, organized by epoch. 
+    /// It is added to the average cost for the gradient, but not included in `avg_costs`.
+    #[inline]
+    pub fn penalties(&self) -> &Vec<Vec<T>> {
+        &self.penalties
+    }
+
+    /// Returns the number of batches in each epoch whose gradient was clipped.
+    #[inline]
+    pub fn clip_counts(&self) -> &Vec<usize> {
+        &self.clip_counts
+    }
+
+    /// Returns every batch in which a non-finite value was found, if training was in anomaly mode.
+    #[inline]
+    pub fn anomalies(&self) -> &Vec<Anomaly> {
+        &self.anomalies
+    }
+
+    /// Returns the exponential moving average of the params, if training kept one.
+    #[inline]
+    pub fn ema_params(&self) -> Option<&Params<T>> {
+        self.ema_params.as_ref()
+    }
+
+    /// Returns the stochastic weight average of the params, if training kept one and reached its start epoch.
+    #[inline]
+    pub fn swa_params(&self) -> Option<&Params<T>> {
+        self.swa_params.as_ref()
+    }
+
     /// Returns the average cost for each epoch.
     #[inline]
     pub fn epoch_cost(&self, dec_places: usize) -> Vec<T> {
@@ -65,6 +107,25 @@ impl<T: Real> TrainingResults<T> {
             .map(|x| (x * ten_power).round() / ten_power)
             .collect()
     }
+
+    /// Converts the params, costs and grads to another precision by way of `f64`.
+    #[inline]
+    pub fn cast<U: Real>(&self) -> TrainingResults<U> {
+        let cast_all = |xs: &Vec<T>| xs.iter().map(|x| U::from_f64(x.to_f64())).collect();
+
+        TrainingResults {
+            layout: self.layout.clone(),
+            params: self.params.cast(),
+            all_costs: self.all_costs.iter().map(|epoch| epoch.iter().map(cast_all).collect()).collect(),
+            avg_costs: self.avg_costs.iter().map(cast_all).collect(),
+            all_grads: self.all_grads.iter().map(|epoch| epoch.iter().map(cast_all).collect()).collect(),
+            penalties: self.penalties.iter().map(cast_all).collect(),
+            clip_counts: self.clip_counts.clone(),
+            anomalies: self.anomalies.clone(),
+            ema_params: self.ema_params.as_ref().map(|params| params.cast()),
+            swa_params: self.swa_params.as_ref().map(|params| params.cast()),
+        }
+    }
 }
 
 impl<T: Real + Serialize + Encode> TrainingResults<T> {
@@ -93,4 +154,36 @@ impl<T: Real + Serialize + Encode> TrainingResults<T> {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use super::*;
+
+    #[test]
+    fn deserialize_test() {
+        let net = Network::builder()
+            .input_layer(2)
+            .feed_forward_layer(ActivationFn::Linear, 1)
+            .build();
+        let res = net.trainer()
+            .data_set(DataSet::builder().sample(vec![0.1, 0.2], vec![0.3]).build())
+            .params(net.default_params())
+            .batch_size(1)
+            .num_epochs(2)
+            .stoch_shuffle_seed(Seed::Input(1.0))
+            .train();
+
+        // results saved before a field was added still load, with the field empty
+        let mut json = serde_json::to_value(&res).unwrap();
+        for field in ["penalties", "clip_counts", "anomalies", "ema_params", "swa_params"] {
+            json.as_object_mut().unwrap().remove(field);
+        }
+        let loaded: TrainingResults<f64> = serde_json::from_value(json).unwrap();
+        assert_eq!(loaded.params(), res.params());
+        assert_eq!(loaded.all_costs(), res.all_costs());
+        assert!(loaded.penalties().is_empty() && loaded.clip_counts().is_empty() && loaded.anomalies().is_empty());
+        assert!(loaded.ema_params().is_none() && loaded.swa_params().is_none());
+    }
+}
@@ -2,10 +2,14 @@ use crate::autodiff::real::Real;
 use crate::autodiff::var::Var;
 use crate::rng::Seed;
 
+use super::anomaly::AnomalyMode;
+use super::backprop::Backend;
 use super::clamp_settings::ClampSettings;
 use super::cost::CostFn;
 use super::data_set::DataSet;
 use super::eta::Eta;
+use super::grad_clip::GradClip;
+use super::regularization::Regularization;
 
 #[derive(Clone, Debug, Default)]
 pub struct TrainingSettings<'t, T: Real> {
@@ -16,6 +20,16 @@ pub struct TrainingSettings<'t, T: Real> {
     pub eta: Eta<T>,
     pub data_set: DataSet<T>,
     pub stoch_shuffle_seed: Seed<T>,
+    pub anomaly_mode: AnomalyMode,
+    /// The number of threads each batch is split across. `0` and `1` compute the batch on the calling thread.
+    pub num_threads: usize,
+    pub backend: Backend,
+    /// Keeps an exponential moving average of the params with this decay, updated after every batch.
+    pub ema_decay: Option<T>,
+    /// Keeps a stochastic weight average of the params at the end of each epoch, starting from this epoch.
+    pub swa_start_epoch: Option<usize>,
+    pub regularization: Regularization<T>,
+    pub grad_clip: GradClip<T>,
 }
 
 impl<'t, T: Real> TrainingSettings<'t, T> {
@@ -74,6 +88,41 @@ impl<'t, T: Real> TrainingSettings<'t, T> {
         &self.stoch_shuffle_seed
     }
 
+    #[inline]
+    pub fn anomaly_mode(&self) -> AnomalyMode {
+        self.anomaly_mode
+    }
+
+    #[inline]
+    pub fn num_threads(&self) -> usize {
+        self.num_threads
+    }
+
+    #[inline]
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    #[inline]
+    pub fn ema_decay(&self) -> Option<T> {
+        self.ema_decay
+    }
+
+    #[inline]
+    pub fn swa_start_epoch(&self) -> Option<usize> {
+        self.swa_start_epoch
+    }
+
+    #[inline]
+    pub fn regularization(&self) -> &Regularization<T> {
+        &self.regularization
+    }
+
+    #[inline]
+    pub fn grad_clip(&self) -> &GradClip<T> {
+        &self.grad_clip
+    }
+
     #[inline]
     pub fn num_batches(&self) -> usize {
         (self.data_set.len() as f32 / self.batch_size as f32).ceil() as usize
@@ -106,7 +155,8 @@ mod tests {
             clamp_settings: ClampSettings::default(),
             eta: Eta::Const(0.00001),
             data_set,
-            stoch_shuffle_seed: Seed::OS
+            stoch_shuffle_seed: Seed::OS,
+            ..Default::default()
         };
 
         assert_eq!(settings.num_batches(), 3);